@@ -1,4 +1,4 @@
-use mcu::{ARMCore, Core, MCUConf};
+use mcu::{ARMCore, Core, MCUConf, Platform};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum CrateType {
@@ -66,6 +66,9 @@ pub struct Cargo {
     authors: Vec<String>,
     dependencies: Vec<Dependency>,
     target: String,
+    // Peripheral access crate providing the interrupt attribute such as "stm32f4"
+    #[serde(default)]
+    pac: String,
 }
 
 impl Cargo {
@@ -81,6 +84,7 @@ impl Cargo {
             authors: Vec::new(),
             dependencies: Vec::new(),
             target: Cargo::set_target(mcu_conf.get_core()),
+            pac: Cargo::default_pac(mcu_conf.get_platform()),
         }
     }
 
@@ -94,6 +98,7 @@ impl Cargo {
         match core {
             &Core::ARM(ref arm) => match arm {
                 &ARMCore::CortexM0 => String::from("thumbv6m-none-eabi"),
+                &ARMCore::CortexM0Plus => String::from("thumbv6m-none-eabi"),
                 &ARMCore::CortexM3 => String::from("thumbv7m-none-eabi"),
                 &ARMCore::CortexM4 => String::from("thumbv7em-none-eabi"),
                 &ARMCore::CortexM7 => String::from("thumbv7em-none-eabi"),
@@ -104,6 +109,22 @@ impl Cargo {
         }
     }
 
+    // stm32-rs crates are named after the family, others use the cortex-m-rt examples name
+    fn default_pac(platform: &Platform) -> String {
+        match *platform {
+            Platform::STM32 { ref family, .. } => family.to_lowercase(),
+            _ => String::from("device"),
+        }
+    }
+
+    pub fn set_pac(&mut self, pac: &str) {
+        self.pac = pac.to_owned();
+    }
+
+    pub fn get_pac(&self) -> &str {
+        &self.pac
+    }
+
     pub fn get_crate_type(&self) -> &CrateType {
         &self.crate_type
     }
@@ -0,0 +1,998 @@
+use errors::*;
+use frequency::Frequency;
+
+const MHZ: u32 = 1_000_000;
+const USB_CLOCK: u32 = 48 * MHZ;
+const APB_PRESCALERS: [u32; 5] = [1, 2, 4, 8, 16];
+const AHB_PRESCALERS: [u32; 9] = [1, 2, 4, 8, 16, 64, 128, 256, 512];
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum ClockSource {
+    Hsi,
+    Hse,
+    Lse,
+    Lsi,
+    Pll,
+    Sysclk,
+    Pclk,
+}
+
+// PLL of F0/F1/F3 multiplies the predivided input, F2/F4/F7 one runs a VCO with M/N/P/Q
+// dividers
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub enum PllKind {
+    Multiplier {
+        prediv: (u32, u32),
+        mul: (u32, u32),
+        // HSI is halved in front of the PLL
+        hsi_div: u32,
+        max: u32,
+    },
+    Vco {
+        m: (u32, u32),
+        n: (u32, u32),
+        q: (u32, u32),
+        input: (u32, u32),
+        vco: (u32, u32),
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub enum Pll {
+    Multiplier {
+        source: ClockSource,
+        prediv: u32,
+        mul: u32,
+    },
+    Vco {
+        source: ClockSource,
+        m: u32,
+        n: u32,
+        p: u32,
+        q: u32,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum Bus {
+    Ahb,
+    Apb1,
+    Apb2,
+}
+
+// Bus clocking the peripheral instance, parts without APB2 have everything on APB1. The
+// IP list of the part names the peripheral kinds but not their buses, hence the table.
+pub fn peripheral_bus(peripheral: &str) -> Option<Bus> {
+    let instance = peripheral.trim_end_matches(|c: char| c.is_ascii_digit());
+    let number = peripheral[instance.len()..].parse::<u8>().unwrap_or(0);
+
+    let bus = match (instance, number) {
+        ("USART", 1) | ("USART", 6) => Bus::Apb2,
+        ("USART", _) | ("UART", _) | ("LPUART", _) => Bus::Apb1,
+        ("SPI", 1) | ("SPI", 4) | ("SPI", 5) | ("SPI", 6) => Bus::Apb2,
+        ("SPI", _) | ("I2S", _) => Bus::Apb1,
+        ("TIM", 1) | ("TIM", 8) | ("TIM", 9) | ("TIM", 10) | ("TIM", 11) => Bus::Apb2,
+        ("TIM", 15) | ("TIM", 16) | ("TIM", 17) | ("TIM", 20) => Bus::Apb2,
+        ("TIM", _) | ("LPTIM", _) => Bus::Apb1,
+        ("ADC", _) | ("SDIO", _) | ("SYSCFG", _) | ("SAI", _) | ("EXTI", _) => Bus::Apb2,
+        ("I2C", _) | ("FMPI2C", _) | ("CAN", _) | ("DAC", _) | ("USB", _) | ("WWDG", _) => {
+            Bus::Apb1
+        }
+        ("PWR", _) | ("CEC", _) | ("CRS", _) | ("RTC", _) | ("SPDIFRX", _) => Bus::Apb1,
+        ("DMA", _) | ("GPIO", _) | ("CRC", _) | ("ETH", _) | ("DCMI", _) | ("RNG", _) => Bus::Ahb,
+        ("USB_OTG_FS", _) | ("USB_OTG_HS", _) | ("FSMC", _) | ("FMC", _) | ("TSC", _) => Bus::Ahb,
+        _ => return None,
+    };
+
+    Some(bus)
+}
+
+// ADC clock divided from a bus clock and limited by the converter
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct AdcClock {
+    bus: Bus,
+    prescalers: Vec<u32>,
+    max: u32,
+}
+
+// Kernel clock selection of a peripheral such as USART1 or I2C1
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Mux {
+    pub peripheral: String,
+    pub source: ClockSource,
+}
+
+// ClockTree class
+//
+//    RCC model of an MCU line: fixed internal oscillators, allowed HSE range, PLL
+//    ranges and the bus limits. Maximal SYSCLK comes from the catalogue, APB limits are
+//    derived from it by the divider of the line.
+//
+//    \sa ClockConfig, ClockRequest
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ClockTree {
+    hsi: u32,
+    lsi: u32,
+    lse: u32,
+    hse: (u32, u32),
+    sysclk_max: u32,
+    apb1_max: u32,
+    // Zero when there's no APB2 bus
+    apb2_max: u32,
+    pll: PllKind,
+    adc: AdcClock,
+}
+
+impl ClockTree {
+    pub fn new(family: &str, line: &str, sysclk_max: Frequency) -> Result<ClockTree> {
+        let sysclk_max = sysclk_max.hz();
+
+        let multiplier = |prediv: (u32, u32), max: u32| PllKind::Multiplier {
+            prediv: prediv,
+            mul: (2, 16),
+            hsi_div: 2,
+            max: max,
+        };
+        let adc = |bus: Bus, prescalers: &[u32], max: u32| AdcClock {
+            bus: bus,
+            prescalers: prescalers.to_vec(),
+            max: max,
+        };
+        let vco = |input: (u32, u32), vco: (u32, u32)| PllKind::Vco {
+            m: (2, 63),
+            n: (50, 432),
+            q: (2, 15),
+            input: input,
+            vco: vco,
+        };
+
+        // Low end F4 lines run APB2 at full SYSCLK and APB1 at half of it
+        let f4_low = [
+            "STM32F401",
+            "STM32F410",
+            "STM32F411",
+            "STM32F412",
+            "STM32F413",
+        ]
+        .iter()
+        .any(|l| line.starts_with(l));
+
+        let tree = match family {
+            "STM32F0" => ClockTree {
+                hsi: 8 * MHZ,
+                lsi: 40_000,
+                lse: 32_768,
+                hse: (4 * MHZ, 32 * MHZ),
+                sysclk_max: sysclk_max,
+                apb1_max: sysclk_max,
+                apb2_max: 0,
+                pll: multiplier((1, 16), sysclk_max),
+                adc: adc(Bus::Apb1, &[2, 4], 14 * MHZ),
+            },
+            "STM32F1" => ClockTree {
+                hsi: 8 * MHZ,
+                lsi: 40_000,
+                lse: 32_768,
+                hse: (4 * MHZ, 16 * MHZ),
+                sysclk_max: sysclk_max,
+                apb1_max: sysclk_max / 2,
+                apb2_max: sysclk_max,
+                pll: multiplier((1, 2), sysclk_max),
+                adc: adc(Bus::Apb2, &[2, 4, 6, 8], 14 * MHZ),
+            },
+            "STM32F3" => ClockTree {
+                hsi: 8 * MHZ,
+                lsi: 40_000,
+                lse: 32_768,
+                hse: (4 * MHZ, 32 * MHZ),
+                sysclk_max: sysclk_max,
+                apb1_max: sysclk_max / 2,
+                apb2_max: sysclk_max,
+                pll: multiplier((1, 16), sysclk_max),
+                adc: adc(Bus::Ahb, &[1, 2, 4], 72 * MHZ),
+            },
+            "STM32F2" | "STM32F4" | "STM32F7" => {
+                let (apb1_div, apb2_div) = if f4_low { (2, 1) } else { (4, 2) };
+                let adc_max = if family == "STM32F2" {
+                    30 * MHZ
+                } else {
+                    36 * MHZ
+                };
+
+                ClockTree {
+                    hsi: 16 * MHZ,
+                    lsi: 32_000,
+                    lse: 32_768,
+                    hse: (4 * MHZ, 26 * MHZ),
+                    sysclk_max: sysclk_max,
+                    apb1_max: sysclk_max / apb1_div,
+                    apb2_max: sysclk_max / apb2_div,
+                    pll: vco((MHZ, 2 * MHZ), (100 * MHZ, 432 * MHZ)),
+                    adc: adc(Bus::Apb2, &[2, 4, 6, 8], adc_max),
+                }
+            }
+            // MSI and voltage scaling ranges of the low power families aren't modelled
+            "STM32L0" | "STM32L1" | "STM32L4" => {
+                return Err(ErrorKind::UnsupportedClockTree(family.to_string()).into())
+            }
+            _ => return Err(ErrorKind::UnknownClockTree(family.to_string()).into()),
+        };
+
+        Ok(tree)
+    }
+
+    pub fn sysclk_max(&self) -> Frequency {
+        Frequency::from_hz(self.sysclk_max)
+    }
+
+    pub fn apb1_max(&self) -> Frequency {
+        Frequency::from_hz(self.apb1_max)
+    }
+
+    // None when there's no APB2 bus
+    pub fn apb2_max(&self) -> Option<Frequency> {
+        match self.apb2_max {
+            0 => None,
+            max => Some(Frequency::from_hz(max)),
+        }
+    }
+
+    pub fn solve(&self, request: &ClockRequest) -> Result<ClockConfig> {
+        if request.sysclk == 0 || request.sysclk > self.sysclk_max {
+            return Err(out_of_range("SYSCLK", request.sysclk));
+        }
+
+        if let Some(hse) = request.hse {
+            if hse < self.hse.0 || hse > self.hse.1 {
+                return Err(out_of_range("HSE", hse));
+            }
+        }
+
+        let mut config = ClockConfig {
+            sysclk_source: ClockSource::Hsi,
+            hse: request.hse.map(Frequency::from_hz),
+            lse: request.lse,
+            pll: None,
+            ahb_prescaler: 1,
+            apb1_prescaler: 1,
+            apb2_prescaler: 1,
+            adc_prescaler: 1,
+            muxes: request.muxes.clone(),
+        };
+
+        let direct = if request.hse == Some(request.sysclk) {
+            Some(ClockSource::Hse)
+        } else if self.hsi == request.sysclk {
+            Some(ClockSource::Hsi)
+        } else {
+            None
+        };
+
+        match direct {
+            Some(source) if !request.usb => config.sysclk_source = source,
+            _ => {
+                config.sysclk_source = ClockSource::Pll;
+                config.pll = Some(
+                    self.solve_pll(request)
+                        .ok_or_else(|| no_solution(request.sysclk))?,
+                );
+            }
+        }
+
+        config.apb1_prescaler = prescaler(request.sysclk, self.apb1_max);
+        config.apb2_prescaler = prescaler(request.sysclk, self.apb2_max);
+        config.adc_prescaler = self.adc_prescaler(&config)?;
+
+        self.clocks(&config)?;
+
+        Ok(config)
+    }
+
+    // PLL settings giving the SYSCLK, HSE is preferred over HSI when fitted
+    fn solve_pll(&self, request: &ClockRequest) -> Option<Pll> {
+        let mut sources = vec![];
+
+        if let Some(hse) = request.hse {
+            sources.push((ClockSource::Hse, hse));
+        }
+        sources.push((ClockSource::Hsi, self.hsi));
+
+        for &(source, freq) in &sources {
+            let pll = match self.pll {
+                PllKind::Multiplier {
+                    prediv,
+                    mul,
+                    hsi_div,
+                    ..
+                } => {
+                    let prediv = match source {
+                        ClockSource::Hsi => (hsi_div, hsi_div),
+                        _ => prediv,
+                    };
+
+                    solve_multiplier(source, freq, prediv, mul, request)
+                }
+                PllKind::Vco {
+                    m,
+                    n,
+                    q,
+                    input,
+                    vco,
+                } => solve_vco(source, freq, (m, n, q), input, vco, request),
+            };
+
+            if pll.is_some() {
+                return pll;
+            }
+        }
+
+        None
+    }
+
+    // Smallest ADC prescaler keeping the converter clock under its limit
+    fn adc_prescaler(&self, config: &ClockConfig) -> Result<u32> {
+        let clocks = self.clocks(&ClockConfig {
+            adc_prescaler: self.adc.prescalers[self.adc.prescalers.len() - 1],
+            ..config.clone()
+        })?;
+        let bus_clock = self.adc_bus_clock(&clocks).hz();
+
+        self.adc
+            .prescalers
+            .iter()
+            .cloned()
+            .find(|p| bus_clock / p <= self.adc.max)
+            .ok_or_else(|| out_of_range("ADC", bus_clock))
+    }
+
+    fn adc_bus_clock(&self, clocks: &Clocks) -> Frequency {
+        match self.adc.bus {
+            Bus::Ahb => clocks.hclk,
+            Bus::Apb1 => clocks.pclk1,
+            Bus::Apb2 => clocks.pclk2.unwrap_or(clocks.pclk1),
+        }
+    }
+
+    // Kernel clock of the peripheral, the mux selection wins over the bus clock and ADCs
+    // run at the prescaled ADC clock
+    pub fn peripheral_clock(&self, config: &ClockConfig, peripheral: &str) -> Result<Frequency> {
+        let clocks = self.clocks(config)?;
+        let bus_clock = self.bus_clock(&clocks, peripheral)?;
+
+        match config.mux(peripheral) {
+            None if peripheral.starts_with("ADC") => Ok(clocks.adc),
+            None | Some(ClockSource::Pclk) => Ok(bus_clock),
+            Some(ClockSource::Sysclk) => Ok(clocks.sysclk),
+            Some(source) => self
+                .source(config, source)
+                .map(Frequency::from_hz)
+                .ok_or_else(|| out_of_range(peripheral, 0)),
+        }
+    }
+
+    // Timers run at twice the APB clock unless the APB prescaler is 1
+    pub fn timer_clock(&self, config: &ClockConfig, timer: &str) -> Result<Frequency> {
+        let clocks = self.clocks(config)?;
+        let bus_clock = self.bus_clock(&clocks, timer)?;
+
+        let prescaler = match self.bus(timer) {
+            Some(Bus::Apb1) => config.apb1_prescaler,
+            Some(Bus::Apb2) => config.apb2_prescaler,
+            _ => 1,
+        };
+
+        match prescaler {
+            1 => Ok(bus_clock),
+            _ => Ok(Frequency::from_hz(bus_clock.hz() * 2)),
+        }
+    }
+
+    fn bus(&self, peripheral: &str) -> Option<Bus> {
+        match peripheral_bus(peripheral) {
+            Some(Bus::Apb2) if self.apb2_max == 0 => Some(Bus::Apb1),
+            bus => bus,
+        }
+    }
+
+    fn bus_clock(&self, clocks: &Clocks, peripheral: &str) -> Result<Frequency> {
+        match self.bus(peripheral) {
+            Some(Bus::Ahb) => Ok(clocks.hclk),
+            Some(Bus::Apb1) => Ok(clocks.pclk1),
+            Some(Bus::Apb2) => Ok(clocks.pclk2.unwrap_or(clocks.pclk1)),
+            None => Err(ErrorKind::UnknownPeripheral(peripheral.to_string()).into()),
+        }
+    }
+
+    // Source frequency, None when the source isn't running
+    fn source(&self, config: &ClockConfig, source: ClockSource) -> Option<u32> {
+        match source {
+            ClockSource::Hsi => Some(self.hsi),
+            ClockSource::Lsi => Some(self.lsi),
+            ClockSource::Hse => config.hse.map(|hse| hse.hz()),
+            ClockSource::Lse if config.lse => Some(self.lse),
+            ClockSource::Lse => None,
+            ClockSource::Pll => config.pll.as_ref().and_then(|pll| {
+                let (source, div, mul) = match *pll {
+                    Pll::Multiplier {
+                        source,
+                        prediv,
+                        mul,
+                    } => (source, prediv, mul),
+                    Pll::Vco {
+                        source, m, n, p, ..
+                    } => (source, m * p, n),
+                };
+
+                self.source(config, source)
+                    .map(|f| (u64::from(f) * u64::from(mul) / u64::from(div)) as u32)
+            }),
+            ClockSource::Sysclk | ClockSource::Pclk => None,
+        }
+    }
+
+    // Derived bus clocks, fails when any of them is out of range
+    pub fn clocks(&self, config: &ClockConfig) -> Result<Clocks> {
+        if config.sysclk_source == ClockSource::Lse || config.sysclk_source == ClockSource::Lsi {
+            return Err(out_of_range("SYSCLK", 0));
+        }
+
+        if let Some(hse) = config.hse.map(|hse| hse.hz()) {
+            if hse < self.hse.0 || hse > self.hse.1 {
+                return Err(out_of_range("HSE", hse));
+            }
+        }
+
+        let pll48 = self.check_pll(config)?;
+
+        let sysclk = self
+            .source(config, config.sysclk_source)
+            .ok_or_else(|| out_of_range("SYSCLK", 0))?;
+
+        if sysclk > self.sysclk_max {
+            return Err(out_of_range("SYSCLK", sysclk));
+        }
+
+        if !AHB_PRESCALERS.contains(&config.ahb_prescaler) {
+            return Err(out_of_range("HCLK", sysclk));
+        }
+
+        let hclk = sysclk / config.ahb_prescaler;
+
+        if !APB_PRESCALERS.contains(&config.apb1_prescaler)
+            || hclk / config.apb1_prescaler > self.apb1_max
+        {
+            return Err(out_of_range("PCLK1", hclk / config.apb1_prescaler));
+        }
+
+        let pclk2 = if self.apb2_max > 0 {
+            if !APB_PRESCALERS.contains(&config.apb2_prescaler)
+                || hclk / config.apb2_prescaler > self.apb2_max
+            {
+                return Err(out_of_range("PCLK2", hclk / config.apb2_prescaler));
+            }
+
+            Some(hclk / config.apb2_prescaler)
+        } else {
+            None
+        };
+
+        for mux in &config.muxes {
+            match mux.source {
+                ClockSource::Sysclk | ClockSource::Pclk => (),
+                source => {
+                    if self.source(config, source).is_none() {
+                        return Err(out_of_range(&mux.peripheral, 0));
+                    }
+                }
+            }
+        }
+
+        let adc_bus = match self.adc.bus {
+            Bus::Ahb => hclk,
+            Bus::Apb1 => hclk / config.apb1_prescaler,
+            Bus::Apb2 => pclk2.unwrap_or(hclk / config.apb1_prescaler),
+        };
+
+        if !self.adc.prescalers.contains(&config.adc_prescaler)
+            || adc_bus / config.adc_prescaler > self.adc.max
+        {
+            return Err(out_of_range("ADC", adc_bus / config.adc_prescaler));
+        }
+
+        Ok(Clocks {
+            sysclk: Frequency::from_hz(sysclk),
+            hclk: Frequency::from_hz(hclk),
+            pclk1: Frequency::from_hz(hclk / config.apb1_prescaler),
+            pclk2: pclk2.map(Frequency::from_hz),
+            pll48: pll48.map(Frequency::from_hz),
+            adc: Frequency::from_hz(adc_bus / config.adc_prescaler),
+        })
+    }
+
+    // Checks PLL settings against the ranges, returns 48 MHz domain clock if there's one
+    fn check_pll(&self, config: &ClockConfig) -> Result<Option<u32>> {
+        let pll = match config.pll {
+            Some(ref pll) => pll,
+            None => return Ok(None),
+        };
+
+        match (pll, &self.pll) {
+            (
+                &Pll::Multiplier {
+                    source,
+                    prediv,
+                    mul,
+                },
+                &PllKind::Multiplier {
+                    prediv: prediv_range,
+                    mul: mul_range,
+                    hsi_div,
+                    max,
+                },
+            ) => {
+                let valid_prediv = match source {
+                    ClockSource::Hsi => prediv == hsi_div,
+                    _ => within(prediv, prediv_range),
+                };
+
+                if !valid_prediv || !within(mul, mul_range) {
+                    return Err(out_of_range("PLL", 0));
+                }
+
+                let out = self
+                    .source(config, ClockSource::Pll)
+                    .ok_or_else(|| out_of_range("PLL", 0))?;
+
+                if out > max {
+                    return Err(out_of_range("PLL", out));
+                }
+
+                // USB prescaler divides by 1 or 1.5
+                if out == USB_CLOCK || out * 2 == USB_CLOCK * 3 {
+                    Ok(Some(USB_CLOCK))
+                } else {
+                    Ok(None)
+                }
+            }
+            (
+                &Pll::Vco { source, m, n, p, q },
+                &PllKind::Vco {
+                    m: m_range,
+                    n: n_range,
+                    q: q_range,
+                    input,
+                    vco,
+                },
+            ) => {
+                let freq = self
+                    .source(config, source)
+                    .ok_or_else(|| out_of_range("PLL", 0))?;
+
+                if !within(m, m_range) || !within(n, n_range) || !within(q, q_range) {
+                    return Err(out_of_range("PLL", 0));
+                }
+
+                if ![2, 4, 6, 8].contains(&p) {
+                    return Err(out_of_range("PLL", 0));
+                }
+
+                let vco_input = freq / m;
+
+                if !within(vco_input, input) {
+                    return Err(out_of_range("PLL input", vco_input));
+                }
+
+                let vco_output = u64::from(vco_input) * u64::from(n);
+
+                if vco_output < u64::from(vco.0) || vco_output > u64::from(vco.1) {
+                    return Err(out_of_range("VCO", vco_output as u32));
+                }
+
+                Ok(Some((vco_output / u64::from(q)) as u32))
+            }
+            _ => Err(out_of_range("PLL", 0)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Clocks {
+    pub sysclk: Frequency,
+    pub hclk: Frequency,
+    pub pclk1: Frequency,
+    pub pclk2: Option<Frequency>,
+    // Clock of USB/SDIO/RNG domain, PLLQ on F2/F4/F7
+    pub pll48: Option<Frequency>,
+    pub adc: Frequency,
+}
+
+// ClockRequest class
+//
+//    Requirements the solver has to meet: SYSCLK, fitted HSE crystal, 48 MHz clock for
+//    USB and peripheral clock muxes.
+//
+//    \sa ClockTree
+#[derive(Debug, Default)]
+pub struct ClockRequest {
+    sysclk: u32,
+    hse: Option<u32>,
+    lse: bool,
+    usb: bool,
+    muxes: Vec<Mux>,
+}
+
+impl ClockRequest {
+    pub fn new(sysclk: Frequency) -> ClockRequest {
+        ClockRequest {
+            sysclk: sysclk.hz(),
+            ..Default::default()
+        }
+    }
+
+    pub fn hse(mut self, freq: Frequency) -> ClockRequest {
+        self.hse = Some(freq.hz());
+        self
+    }
+
+    pub fn lse(mut self) -> ClockRequest {
+        self.lse = true;
+        self
+    }
+
+    pub fn usb(mut self) -> ClockRequest {
+        self.usb = true;
+        self
+    }
+
+    pub fn mux(mut self, peripheral: &str, source: ClockSource) -> ClockRequest {
+        self.muxes.push(Mux {
+            peripheral: peripheral.to_string(),
+            source: source,
+        });
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ClockConfig {
+    pub sysclk_source: ClockSource,
+    pub hse: Option<Frequency>,
+    pub lse: bool,
+    pub pll: Option<Pll>,
+    pub ahb_prescaler: u32,
+    pub apb1_prescaler: u32,
+    pub apb2_prescaler: u32,
+    pub adc_prescaler: u32,
+    pub muxes: Vec<Mux>,
+}
+
+impl ClockConfig {
+    pub fn mux(&self, peripheral: &str) -> Option<ClockSource> {
+        self.muxes
+            .iter()
+            .find(|m| m.peripheral == peripheral)
+            .map(|m| m.source)
+    }
+}
+
+fn solve_multiplier(
+    source: ClockSource,
+    freq: u32,
+    prediv: (u32, u32),
+    mul: (u32, u32),
+    request: &ClockRequest,
+) -> Option<Pll> {
+    for div in prediv.0..prediv.1 + 1 {
+        for factor in mul.0..mul.1 + 1 {
+            let out = u64::from(freq) * u64::from(factor);
+
+            if out != u64::from(request.sysclk) * u64::from(div) {
+                continue;
+            }
+
+            let sysclk = request.sysclk;
+
+            if request.usb && sysclk != USB_CLOCK && sysclk * 2 != USB_CLOCK * 3 {
+                continue;
+            }
+
+            return Some(Pll::Multiplier {
+                source: source,
+                prediv: div,
+                mul: factor,
+            });
+        }
+    }
+
+    None
+}
+
+// Dividers are the (M, N, Q) ranges of the PLL
+fn solve_vco(
+    source: ClockSource,
+    freq: u32,
+    dividers: ((u32, u32), (u32, u32), (u32, u32)),
+    input: (u32, u32),
+    vco: (u32, u32),
+    request: &ClockRequest,
+) -> Option<Pll> {
+    let (m_range, n_range, q_range) = dividers;
+
+    // Higher VCO input lowers PLL jitter, so the smallest M goes first
+    for m in m_range.0..m_range.1 + 1 {
+        if freq % m != 0 || !within(freq / m, input) {
+            continue;
+        }
+
+        let vco_input = u64::from(freq / m);
+
+        for &p in &[2u32, 4, 6, 8] {
+            let vco_output = u64::from(request.sysclk) * u64::from(p);
+
+            if vco_output % vco_input != 0
+                || vco_output < u64::from(vco.0)
+                || vco_output > u64::from(vco.1)
+            {
+                continue;
+            }
+
+            let n = (vco_output / vco_input) as u32;
+
+            if !within(n, n_range) {
+                continue;
+            }
+
+            let q = if vco_output % u64::from(USB_CLOCK) == 0
+                && within((vco_output / u64::from(USB_CLOCK)) as u32, q_range)
+            {
+                (vco_output / u64::from(USB_CLOCK)) as u32
+            } else if request.usb {
+                continue;
+            } else {
+                // Keep 48 MHz domain under its limit
+                ((vco_output + u64::from(USB_CLOCK) - 1) / u64::from(USB_CLOCK)) as u32
+            };
+
+            return Some(Pll::Vco {
+                source: source,
+                m: m,
+                n: n,
+                p: p,
+                q: q.max(q_range.0).min(q_range.1),
+            });
+        }
+    }
+
+    None
+}
+
+// Smallest prescaler keeping the bus under its limit
+fn prescaler(freq: u32, max: u32) -> u32 {
+    APB_PRESCALERS
+        .iter()
+        .cloned()
+        .find(|p| freq / p <= max)
+        .unwrap_or(1)
+}
+
+fn within(value: u32, range: (u32, u32)) -> bool {
+    value >= range.0 && value <= range.1
+}
+
+fn out_of_range(clock: &str, freq: u32) -> Error {
+    ErrorKind::ClockOutOfRange(clock.to_string(), Frequency::from_hz(freq)).into()
+}
+
+fn no_solution(sysclk: u32) -> Error {
+    ErrorKind::NoClockSolution(Frequency::from_hz(sysclk)).into()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn mhz(freq: u32) -> Frequency {
+        Frequency::from_mhz(freq)
+    }
+
+    #[test]
+    fn solve_f0_hsi() {
+        let tree = ClockTree::new("STM32F0", "STM32F0x0 Value Line", mhz(48)).unwrap();
+        let config = tree.solve(&ClockRequest::new(mhz(48))).unwrap();
+
+        assert_eq!(config.sysclk_source, ClockSource::Pll);
+        assert_eq!(
+            config.pll,
+            Some(Pll::Multiplier {
+                source: ClockSource::Hsi,
+                prediv: 2,
+                mul: 12,
+            })
+        );
+
+        let clocks = tree.clocks(&config).unwrap();
+        assert_eq!(clocks.sysclk, mhz(48));
+        assert_eq!(clocks.pclk1, mhz(48));
+        assert_eq!(clocks.pclk2, None);
+        assert_eq!(clocks.pll48, Some(mhz(48)));
+    }
+
+    #[test]
+    fn solve_direct() {
+        let tree = ClockTree::new("STM32F1", "STM32F103", mhz(72)).unwrap();
+
+        let config = tree.solve(&ClockRequest::new(mhz(8)).hse(mhz(8))).unwrap();
+        assert_eq!(config.sysclk_source, ClockSource::Hse);
+        assert_eq!(config.pll, None);
+
+        let config = tree.solve(&ClockRequest::new(mhz(8))).unwrap();
+        assert_eq!(config.sysclk_source, ClockSource::Hsi);
+    }
+
+    #[test]
+    fn solve_f1_hse_apb() {
+        let tree = ClockTree::new("STM32F1", "STM32F103", mhz(72)).unwrap();
+        let config = tree
+            .solve(&ClockRequest::new(mhz(72)).hse(mhz(8)).usb())
+            .unwrap();
+
+        assert_eq!(
+            config.pll,
+            Some(Pll::Multiplier {
+                source: ClockSource::Hse,
+                prediv: 1,
+                mul: 9,
+            })
+        );
+        assert_eq!(config.apb1_prescaler, 2);
+        assert_eq!(config.apb2_prescaler, 1);
+        assert_eq!(tree.clocks(&config).unwrap().pclk1, mhz(36));
+    }
+
+    #[test]
+    fn solve_f4_usb() {
+        let tree = ClockTree::new("STM32F4", "STM32F407/417", mhz(168)).unwrap();
+        let config = tree
+            .solve(&ClockRequest::new(mhz(168)).hse(mhz(8)).usb())
+            .unwrap();
+
+        assert_eq!(
+            config.pll,
+            Some(Pll::Vco {
+                source: ClockSource::Hse,
+                m: 4,
+                n: 168,
+                p: 2,
+                q: 7,
+            })
+        );
+
+        let clocks = tree.clocks(&config).unwrap();
+        assert_eq!(clocks.sysclk, mhz(168));
+        assert_eq!(clocks.pclk1, mhz(42));
+        assert_eq!(clocks.pclk2, Some(mhz(84)));
+        assert_eq!(clocks.pll48, Some(mhz(48)));
+    }
+
+    #[test]
+    fn solve_out_of_range() {
+        let tree = ClockTree::new("STM32F4", "STM32F407/417", mhz(168)).unwrap();
+
+        match tree.solve(&ClockRequest::new(mhz(200))) {
+            Err(Error(ErrorKind::ClockOutOfRange(ref clock, freq), _)) => {
+                assert_eq!(clock, "SYSCLK");
+                assert_eq!(freq, mhz(200));
+            }
+            _ => assert!(false),
+        }
+
+        assert!(tree
+            .solve(&ClockRequest::new(mhz(84)).hse(mhz(40)))
+            .is_err());
+        assert!(tree
+            .solve(&ClockRequest::new(Frequency::from_hz(100 * MHZ + 1)))
+            .is_err());
+        assert!(ClockTree::new("STM32H7", "STM32H743", mhz(400)).is_err());
+    }
+
+    #[test]
+    fn apb_limits_per_line() {
+        let tree = ClockTree::new("STM32F4", "STM32F411", mhz(100)).unwrap();
+        let config = tree
+            .solve(&ClockRequest::new(mhz(100)).hse(mhz(25)))
+            .unwrap();
+
+        assert_eq!(config.apb1_prescaler, 2);
+        assert_eq!(config.apb2_prescaler, 1);
+
+        let clocks = tree.clocks(&config).unwrap();
+        assert_eq!(clocks.pclk1, mhz(50));
+        assert_eq!(clocks.pclk2, Some(mhz(100)));
+
+        // PCLK2 of 84 MHz is within the limit of F401
+        let tree = ClockTree::new("STM32F4", "STM32F401", mhz(84)).unwrap();
+        let mut config = tree.solve(&ClockRequest::new(mhz(84))).unwrap();
+        config.apb2_prescaler = 1;
+        assert_eq!(tree.clocks(&config).unwrap().pclk2, Some(mhz(84)));
+
+        let tree = ClockTree::new("STM32F4", "STM32F446", mhz(180)).unwrap();
+        assert_eq!(tree.apb1_max(), mhz(45));
+        assert_eq!(tree.apb2_max(), Some(mhz(90)));
+    }
+
+    #[test]
+    fn unsupported_families() {
+        for family in &["STM32L0", "STM32L1", "STM32L4"] {
+            match ClockTree::new(family, "", mhz(32)) {
+                Err(Error(ErrorKind::UnsupportedClockTree(ref name), _)) => {
+                    assert_eq!(name, family)
+                }
+                _ => panic!("{} has no clock tree model", family),
+            }
+        }
+    }
+
+    #[test]
+    fn bus_mapping() {
+        assert_eq!(peripheral_bus("USART1"), Some(Bus::Apb2));
+        assert_eq!(peripheral_bus("USART2"), Some(Bus::Apb1));
+        assert_eq!(peripheral_bus("TIM16"), Some(Bus::Apb2));
+        assert_eq!(peripheral_bus("TIM3"), Some(Bus::Apb1));
+        assert_eq!(peripheral_bus("DMA2"), Some(Bus::Ahb));
+        assert_eq!(peripheral_bus("XYZ1"), None);
+    }
+
+    #[test]
+    fn peripheral_clocks_f4() {
+        let tree = ClockTree::new("STM32F4", "STM32F407/417", mhz(168)).unwrap();
+        let config = tree
+            .solve(&ClockRequest::new(mhz(168)).hse(mhz(8)))
+            .unwrap();
+
+        assert_eq!(tree.peripheral_clock(&config, "USART1").unwrap(), mhz(84));
+        assert_eq!(tree.peripheral_clock(&config, "USART2").unwrap(), mhz(42));
+        assert_eq!(tree.timer_clock(&config, "TIM3").unwrap(), mhz(84));
+        assert_eq!(tree.timer_clock(&config, "TIM1").unwrap(), mhz(168));
+        assert!(tree.peripheral_clock(&config, "XYZ1").is_err());
+
+        // PCLK2 of 84 MHz is divided by 4 to stay under the 36 MHz limit of the ADC
+        assert_eq!(config.adc_prescaler, 4);
+        assert_eq!(tree.peripheral_clock(&config, "ADC1").unwrap(), mhz(21));
+
+        let mut config = config.clone();
+        config.adc_prescaler = 2;
+        assert!(tree.clocks(&config).is_err());
+    }
+
+    #[test]
+    fn peripheral_clocks_f0_mux() {
+        let tree = ClockTree::new("STM32F0", "STM32F0x0 Value Line", mhz(48)).unwrap();
+        let config = tree
+            .solve(&ClockRequest::new(mhz(48)).mux("USART1", ClockSource::Hsi))
+            .unwrap();
+
+        assert_eq!(tree.peripheral_clock(&config, "USART1").unwrap(), mhz(8));
+        assert_eq!(tree.peripheral_clock(&config, "USART2").unwrap(), mhz(48));
+        assert_eq!(tree.timer_clock(&config, "TIM1").unwrap(), mhz(48));
+    }
+
+    #[test]
+    fn clocks_invalid_config() {
+        let tree = ClockTree::new("STM32F4", "STM32F407/417", mhz(168)).unwrap();
+        let mut config = tree.solve(&ClockRequest::new(mhz(168))).unwrap();
+
+        config.apb1_prescaler = 2;
+        assert!(tree.clocks(&config).is_err());
+
+        config.apb1_prescaler = 4;
+        config.muxes.push(Mux {
+            peripheral: "RTC".to_string(),
+            source: ClockSource::Lse,
+        });
+        assert!(tree.clocks(&config).is_err());
+
+        config.lse = true;
+        assert!(tree.clocks(&config).is_ok());
+    }
+}
@@ -0,0 +1,345 @@
+use std::fmt;
+
+use gpio::port_name;
+use pins::Pins;
+use signal::SignalKind;
+
+// Functions a peripheral needs routed, one of each group
+const REQUIRED: [(&str, &[&[&str]]); 11] = [
+    ("SPI", &[&["SCK"], &["MOSI", "MISO"]]),
+    ("I2S", &[&["CK"], &["SD"], &["WS"]]),
+    ("I2C", &[&["SCL"], &["SDA"]]),
+    ("FMPI2C", &[&["SCL"], &["SDA"]]),
+    ("USART", &[&["TX", "RX"]]),
+    ("UART", &[&["TX", "RX"]]),
+    ("LPUART", &[&["TX", "RX"]]),
+    ("CAN", &[&["RX"], &["TX"]]),
+    ("SDIO", &[&["CK"], &["CMD"], &["D0"]]),
+    ("USB_OTG_FS", &[&["DM"], &["DP"]]),
+    ("USB_OTG_HS", &[&["DM"], &["DP"]]),
+];
+
+#[derive(Debug, PartialEq)]
+pub enum Conflict {
+    // Configured package pins bonded to the same GPIO port pin
+    SharedPin {
+        port: String,
+        pins: Vec<String>,
+    },
+    // Signal selected on more than one pin
+    DuplicatedSignal {
+        signal: String,
+        pins: Vec<String>,
+    },
+    // Peripheral with some of its signals routed and required ones missing
+    HalfRouted {
+        instance: String,
+        missing: Vec<String>,
+    },
+    // Pin configured differently from the signal the user requires on it
+    UnmetRequirement {
+        pin: String,
+        signal: String,
+    },
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Conflict::SharedPin { ref port, ref pins } => {
+                write!(f, "{} is assigned on pins {}", port, pins.join(", "))
+            }
+            Conflict::DuplicatedSignal {
+                ref signal,
+                ref pins,
+            } => write!(f, "{} is selected on pins {}", signal, pins.join(", ")),
+            Conflict::HalfRouted {
+                ref instance,
+                ref missing,
+            } => write!(f, "{} is missing {}", instance, missing.join(", ")),
+            Conflict::UnmetRequirement {
+                ref pin,
+                ref signal,
+            } => write!(f, "{} requires {}", pin, signal),
+        }
+    }
+}
+
+// ConflictReport class
+//
+//    Result of checking pin assignments, empty report means no conflicts.
+//
+//    \sa Pins, Conflict
+#[derive(Debug, PartialEq)]
+pub struct ConflictReport {
+    conflicts: Vec<Conflict>,
+}
+
+impl ConflictReport {
+    pub fn conflicts(&self) -> &Vec<Conflict> {
+        &self.conflicts
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+impl fmt::Display for ConflictReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for conflict in &self.conflicts {
+            writeln!(f, "{}", conflict)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn check(pins: &Pins) -> ConflictReport {
+    let mut conflicts = vec![];
+
+    // Package pin name with selected signal
+    let mut assigned = vec![];
+
+    for pin in pins.pins() {
+        if let Some(signal) = pin.params().and_then(|p| p.current()) {
+            assigned.push((pin.name(), signal));
+        }
+    }
+
+    let mut ports: Vec<&str> = vec![];
+
+    for &(name, _) in &assigned {
+        let port = port_name(name);
+
+        if ports.contains(&port) {
+            continue;
+        }
+        ports.push(port);
+
+        let shared: Vec<String> = assigned
+            .iter()
+            .filter(|a| port_name(a.0) == port)
+            .map(|a| a.0.to_string())
+            .collect();
+
+        if shared.len() > 1 {
+            conflicts.push(Conflict::SharedPin {
+                port: port.to_string(),
+                pins: shared,
+            });
+        }
+    }
+
+    let mut signals: Vec<&str> = vec![];
+
+    for &(_, signal) in &assigned {
+        if signal.kind() == SignalKind::Gpio || signals.contains(&signal.name()) {
+            continue;
+        }
+        signals.push(signal.name());
+
+        let duplicated: Vec<String> = assigned
+            .iter()
+            .filter(|a| a.1.name() == signal.name())
+            .map(|a| a.0.to_string())
+            .collect();
+
+        if duplicated.len() > 1 {
+            conflicts.push(Conflict::DuplicatedSignal {
+                signal: signal.name().to_string(),
+                pins: duplicated,
+            });
+        }
+    }
+
+    let mut instances: Vec<&str> = vec![];
+
+    for &(_, signal) in &assigned {
+        let instance = signal.instance();
+
+        if instances.contains(&instance) {
+            continue;
+        }
+        instances.push(instance);
+
+        let peripheral = instance.trim_end_matches(|c: char| c.is_ascii_digit());
+        let groups = match REQUIRED.iter().find(|r| r.0 == peripheral) {
+            Some(&(_, groups)) => groups,
+            None => continue,
+        };
+
+        let routed: Vec<&str> = assigned
+            .iter()
+            .filter(|a| a.1.instance() == instance)
+            .map(|a| a.1.function())
+            .collect();
+
+        let missing: Vec<String> = groups
+            .iter()
+            .filter(|group| !group.iter().any(|f| routed.contains(f)))
+            .map(|group| group.join("/"))
+            .collect();
+
+        if !missing.is_empty() {
+            conflicts.push(Conflict::HalfRouted {
+                instance: instance.to_string(),
+                missing: missing,
+            });
+        }
+    }
+
+    for pin in pins.pins() {
+        let params = match pin.params() {
+            Some(params) => params,
+            None => continue,
+        };
+
+        if let Some(required) = params.required_signal() {
+            if params.current_signal() != Some(required) {
+                conflicts.push(Conflict::UnmetRequirement {
+                    pin: pin.name().to_string(),
+                    signal: required.to_string(),
+                });
+            }
+        }
+    }
+
+    ConflictReport {
+        conflicts: conflicts,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use mcu::MCU;
+    use pin::{PinBuilder, Position};
+    use std::path::Path;
+
+    fn select(pins: &mut Pins, signal: &str, nth: usize) {
+        let idx = pins.find_pin(signal)[nth];
+        pins.pins[idx].params_mut().unwrap().select_signal(signal);
+    }
+
+    #[test]
+    fn no_conflicts() {
+        let sample = Path::new("./samples/STM32F030C6Tx.json");
+        let mut mcu_conf = MCU::new(sample).unwrap().finish();
+
+        assert!(mcu_conf.get_pins().check_conflicts().is_ok());
+
+        let pins = mcu_conf.get_pins_mut();
+        select(pins, "SPI1_SCK", 0);
+        select(pins, "SPI1_MISO", 0);
+        select(pins, "USART1_TX", 0);
+
+        assert!(pins.check_conflicts().is_ok());
+        assert!(pins.validate().is_ok());
+    }
+
+    #[test]
+    fn half_routed_and_duplicated() {
+        let sample = Path::new("./samples/STM32F030C6Tx.json");
+        let mut mcu_conf = MCU::new(sample).unwrap().finish();
+
+        let pins = mcu_conf.get_pins_mut();
+        select(pins, "SPI1_SCK", 0);
+        select(pins, "USART1_TX", 0);
+        select(pins, "USART1_TX", 1);
+
+        let report = pins.check_conflicts();
+        assert_eq!(
+            *report.conflicts(),
+            vec![
+                Conflict::DuplicatedSignal {
+                    signal: "USART1_TX".to_string(),
+                    pins: vec!["PA2".to_string(), "PA9".to_string()],
+                },
+                Conflict::HalfRouted {
+                    instance: "SPI1".to_string(),
+                    missing: vec!["MOSI/MISO".to_string()],
+                },
+            ]
+        );
+        assert_eq!(
+            report.to_string(),
+            "USART1_TX is selected on pins PA2, PA9\nSPI1 is missing MOSI/MISO\n"
+        );
+        assert!(pins.validate().is_err());
+    }
+
+    #[test]
+    fn shared_pin() {
+        let pins = Pins {
+            pins: vec![
+                PinBuilder::new("I/O", Position::Linear(17), "PA9")
+                    .signals(vec![String::from("USART1_TX")], 0)
+                    .finish(),
+                PinBuilder::new("I/O", Position::Linear(17), "PA11 [PA9]")
+                    .signals(vec![String::from("GPIO")], 0)
+                    .finish(),
+                PinBuilder::new("I/O", Position::Linear(18), "PA11")
+                    .signals(vec![String::from("GPIO")], 0)
+                    .finish(),
+                // Unconfigured pin doesn't conflict
+                PinBuilder::new("I/O", Position::Linear(19), "PA9")
+                    .reset_signals(vec![String::from("GPIO")])
+                    .finish(),
+            ],
+        };
+
+        assert_eq!(
+            *pins.check_conflicts().conflicts(),
+            vec![Conflict::SharedPin {
+                port: "PA11".to_string(),
+                pins: vec!["PA11 [PA9]".to_string(), "PA11".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn half_routed_usb() {
+        let pins = Pins {
+            pins: vec![
+                PinBuilder::new("I/O", Position::Linear(44), "PA11")
+                    .signals(vec![String::from("USB_OTG_FS_DM")], 0)
+                    .finish(),
+                PinBuilder::new("I/O", Position::Linear(45), "PA12")
+                    .reset_signals(vec![String::from("USB_OTG_FS_DP")])
+                    .finish(),
+            ],
+        };
+
+        assert_eq!(pins.find_instance("USB_OTG_FS"), vec![0, 1]);
+        assert_eq!(
+            *pins.check_conflicts().conflicts(),
+            vec![Conflict::HalfRouted {
+                instance: "USB_OTG_FS".to_string(),
+                missing: vec!["DP".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn unmet_requirement() {
+        let sample = Path::new("./samples/STM32F030C6Tx.json");
+        let mut mcu_conf = MCU::new(sample).unwrap().finish();
+
+        let pins = mcu_conf.get_pins_mut();
+        let idx = pins.find_pin("PA9")[0];
+        assert!(pins.pins[idx]
+            .params_mut()
+            .unwrap()
+            .require_signal("USART1_TX"));
+
+        assert_eq!(
+            pins.check_conflicts().to_string(),
+            "PA9 requires USART1_TX\n"
+        );
+
+        pins.assign_peripheral_pins(&["USART1_TX"]).unwrap();
+        assert!(pins.check_conflicts().is_ok());
+    }
+}
@@ -0,0 +1,774 @@
+use std::fs::File;
+use std::path::Path;
+
+use serde_json;
+
+use errors::*;
+use gpio::GpioModes;
+use mcu::{ARMCore, Core, Frequency, Platform, IP, MCU};
+use memory::{flash_sectors, Memory};
+use package::Package;
+use pin::{PinBuilder, Position};
+use pins::Pins;
+
+const FLASH_START: u32 = 0x0800_0000;
+const RAM_START: u32 = 0x2000_0000;
+const EEPROM_START: u32 = 0x0808_0000;
+const RAM2_START: u32 = 0x1000_0000;
+
+// RAM of the catalogue in KiB counts in CCM of F3 and F4 and SRAM2 of L4, which lie
+// apart at RAM2_START. Listed as part prefix, catalogue RAM and size of the second bank.
+const SECOND_RAM: [(&str, u32, u32); 21] = [
+    ("STM32F303", 16, 4),
+    ("STM32F303", 40, 8),
+    ("STM32F303", 48, 8),
+    ("STM32F303", 80, 16),
+    ("STM32F328", 16, 4),
+    ("STM32F334", 16, 4),
+    ("STM32F358", 48, 8),
+    ("STM32F398", 80, 16),
+    ("STM32F405", 192, 64),
+    ("STM32F407", 192, 64),
+    ("STM32F415", 192, 64),
+    ("STM32F417", 192, 64),
+    ("STM32F427", 256, 64),
+    ("STM32F429", 256, 64),
+    ("STM32F437", 256, 64),
+    ("STM32F439", 256, 64),
+    ("STM32F469", 384, 64),
+    ("STM32F479", 384, 64),
+    ("STM32L4", 40, 8),
+    ("STM32L4", 64, 16),
+    ("STM32L4", 128, 32),
+];
+
+// Raw layout of the catalogue as exported from CubeMX
+#[derive(Deserialize, Debug)]
+struct RawDatabase {
+    #[serde(rename = "Families")] families: RawFamilies,
+}
+
+#[derive(Deserialize, Debug)]
+struct RawFamilies {
+    #[serde(rename = "Family")] family: Vec<RawFamily>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RawFamily {
+    #[serde(rename = "Name")] name: String,
+    #[serde(rename = "Line")] line: Vec<RawLine>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RawLine {
+    #[serde(rename = "Name")] name: String,
+    #[serde(rename = "Mcu")] mcu: Vec<RawMcu>,
+}
+
+// Frequency is missing for some parts and stored as empty string
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum RawFrequency {
+    MHz(u16),
+    #[allow(dead_code)] Missing(String),
+}
+
+#[derive(Deserialize, Debug)]
+struct RawMcu {
+    #[serde(rename = "Name")] name: String,
+    #[serde(rename = "Core")] core: String,
+    #[serde(rename = "Flash")] flash: u32,
+    #[serde(rename = "Ram")] ram: u32,
+    #[serde(rename = "Eeprom")] eeprom: u32,
+    #[serde(rename = "Frequency")] frequency: RawFrequency,
+    #[serde(rename = "IOs")] ios: u16,
+    #[serde(rename = "Package")] package: String,
+    #[serde(rename = "IP")] ips: Vec<RawIP>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RawIP {
+    #[serde(rename = "Inst")] inst: u16,
+    #[serde(rename = "Type")] ip_type: String,
+}
+
+// Pinout as listed in the MCU file of CubeMX such as STM32F446RCTx
+#[derive(Deserialize, Debug)]
+struct RawPinout {
+    #[serde(rename = "Mcu")]
+    mcu: RawPinoutMcu,
+}
+
+#[derive(Deserialize, Debug)]
+struct RawPinoutMcu {
+    #[serde(rename = "Package")]
+    package: String,
+    #[serde(rename = "Pin")]
+    pins: Vec<RawPackagePin>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RawPackagePin {
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "Position")]
+    position: String,
+    #[serde(rename = "Type")]
+    pin_type: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct PackagePin {
+    pub name: String,
+    pub position: Position,
+    // Pin type as taken by PinBuilder such as "I/O" or "Power"
+    pub pin_type: String,
+}
+
+// McuPinout class
+//
+//    Pins of the part with their positions on the package and their types.
+//
+//    \sa McuDatabase, PinBuilder
+#[derive(Serialize, Deserialize, Debug)]
+pub struct McuPinout {
+    package: Package,
+    pins: Vec<PackagePin>,
+}
+
+impl McuPinout {
+    pub fn new(path: &Path) -> Result<McuPinout> {
+        let file = File::open(path)?;
+        let raw: RawPinout = serde_json::from_reader(file)?;
+
+        let mut pins = Vec::with_capacity(raw.mcu.pins.len());
+
+        for pin in raw.mcu.pins {
+            let pin_type = match pin.pin_type.as_str() {
+                "I/O" | "MonoIO" => "I/O",
+                "Power" => "Power",
+                "Reset" => "Reset",
+                "Boot" => "BOOT",
+                "NC" => "NC",
+                other => {
+                    return Err(ErrorKind::InvalidPinout(format!(
+                        "unknown type '{}' of pin {}",
+                        other, pin.name
+                    ))
+                    .into())
+                }
+            };
+
+            pins.push(PackagePin {
+                position: pin.position.parse()?,
+                name: pin.name,
+                pin_type: pin_type.to_string(),
+            });
+        }
+
+        Ok(McuPinout {
+            package: raw.mcu.package.parse()?,
+            pins: pins,
+        })
+    }
+
+    pub fn package(&self) -> &Package {
+        &self.package
+    }
+
+    pub fn pins(&self) -> &Vec<PackagePin> {
+        &self.pins
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct IPInstances {
+    pub ip_type: String,
+    pub count: u16,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct McuInfo {
+    pub name: String,
+    pub core: Core,
+    pub frequency: Option<Frequency>,
+    pub memory: Vec<Memory>,
+    pub ios: u16,
+    pub package: Package,
+    pub ips: Vec<IPInstances>,
+}
+
+impl McuInfo {
+    fn from_raw(raw: RawMcu, family: &str) -> Result<McuInfo> {
+        let second = SECOND_RAM
+            .iter()
+            .find(|r| raw.name.starts_with(r.0) && raw.ram == r.1)
+            .map_or(0, |r| r.2);
+
+        let mut memory = vec![
+            Memory::Flash {
+                start: FLASH_START,
+                size: raw.flash * 1024,
+                sectors: flash_sectors(&raw.name, raw.flash * 1024),
+            },
+            Memory::Ram {
+                start: RAM_START,
+                size: (raw.ram - second) * 1024,
+            },
+        ];
+
+        if second > 0 && family == "STM32L4" {
+            memory.push(Memory::Sram {
+                name: String::from("SRAM2"),
+                start: RAM2_START,
+                size: second * 1024,
+            });
+        } else if second > 0 {
+            memory.push(Memory::Ccm {
+                start: RAM2_START,
+                size: second * 1024,
+            });
+        }
+
+        // Data EEPROM is stated in bytes unlike Flash and RAM in KiB
+        if raw.eeprom > 0 {
+            memory.push(Memory::Eeprom {
+                start: EEPROM_START,
+                size: raw.eeprom,
+            });
+        }
+
+        let frequency = match raw.frequency {
+            RawFrequency::MHz(mhz) => Some(
+                Frequency::checked_from_mhz(u32::from(mhz))
+                    .ok_or_else(|| ErrorKind::InvalidFrequency(format!("{}MHz", mhz)))?,
+            ),
+            RawFrequency::Missing(_) => None,
+        };
+
+        Ok(McuInfo {
+            core: parse_core(&raw.core)?,
+            frequency: frequency,
+            memory: memory,
+            ios: raw.ios,
+            package: raw.package.parse()?,
+            ips: raw.ips
+                .into_iter()
+                .map(|ip| IPInstances {
+                    ip_type: ip.ip_type,
+                    count: ip.inst,
+                })
+                .collect(),
+            name: raw.name,
+        })
+    }
+
+    pub fn flash_size(&self) -> u32 {
+        self.memory
+            .iter()
+            .filter(|m| matches!(**m, Memory::Flash { .. }))
+            .map(|m| m.size())
+            .sum()
+    }
+
+    pub fn ram_size(&self) -> u32 {
+        self.memory
+            .iter()
+            .filter(|m| m.is_ram())
+            .map(|m| m.size())
+            .sum()
+    }
+
+    pub fn ip_count(&self, ip_type: &str) -> u16 {
+        match self.ips.iter().find(|ip| ip.ip_type == ip_type) {
+            Some(ip) => ip.count,
+            None => 0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Line {
+    pub name: String,
+    pub mcus: Vec<McuInfo>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Family {
+    pub name: String,
+    pub lines: Vec<Line>,
+}
+
+// Catalogue entry together with the family and line it belongs to
+#[derive(Debug, Clone, Copy)]
+pub struct McuEntry<'a> {
+    pub family: &'a Family,
+    pub line: &'a Line,
+    pub info: &'a McuInfo,
+}
+
+// McuDatabase class
+//
+//    Catalogue of all supported parts grouped into families and lines.
+//
+//    \sa MCU, McuInfo
+#[derive(Serialize, Deserialize, Debug)]
+pub struct McuDatabase {
+    families: Vec<Family>,
+}
+
+impl McuDatabase {
+    pub fn new(path: &Path) -> Result<McuDatabase> {
+        let file = File::open(path)?;
+        let raw: RawDatabase = serde_json::from_reader(file)?;
+
+        let mut families = Vec::with_capacity(raw.families.family.len());
+
+        for family in raw.families.family {
+            let mut lines = Vec::with_capacity(family.line.len());
+
+            for line in family.line {
+                let mut mcus = Vec::with_capacity(line.mcu.len());
+
+                for mcu in line.mcu {
+                    mcus.push(McuInfo::from_raw(mcu, &family.name)?);
+                }
+
+                lines.push(Line {
+                    name: line.name,
+                    mcus: mcus,
+                });
+            }
+
+            families.push(Family {
+                name: family.name,
+                lines: lines,
+            });
+        }
+
+        Ok(McuDatabase { families: families })
+    }
+
+    pub fn families(&self) -> &Vec<Family> {
+        &self.families
+    }
+
+    pub fn find_family(&self, name: &str) -> Option<&Family> {
+        self.families.iter().find(|f| f.name == name)
+    }
+
+    pub fn mcus(&self) -> Vec<&McuInfo> {
+        let mut mcus: Vec<&McuInfo> = vec![];
+
+        for family in &self.families {
+            for line in &family.lines {
+                mcus.extend(line.mcus.iter());
+            }
+        }
+
+        mcus
+    }
+
+    // Finds all parts of the name, names such as "STM32F407V(E-G)Tx" cover variants
+    // differing only in Flash size
+    pub fn find_mcus(&self, name: &str) -> Vec<McuEntry<'_>> {
+        let mut found = vec![];
+
+        for family in &self.families {
+            for line in &family.lines {
+                for mcu in line.mcus.iter().filter(|m| m.name == name) {
+                    found.push(McuEntry {
+                        family: family,
+                        line: line,
+                        info: mcu,
+                    });
+                }
+            }
+        }
+
+        found
+    }
+
+    // Finds part by name and Flash size in bytes which together identify it
+    pub fn find_mcu(&self, name: &str, flash: u32) -> Option<McuEntry<'_>> {
+        self.find_mcus(name)
+            .into_iter()
+            .find(|entry| entry.info.flash_size() == flash)
+    }
+
+    // Synthesizes full MCU description from the catalogue entry, its pinout and GPIO
+    // mode file
+    pub fn build_mcu(
+        &self,
+        name: &str,
+        flash: u32,
+        pinout: &McuPinout,
+        gpio: &GpioModes,
+    ) -> Result<MCU> {
+        let (family, line, info) = match self.find_mcu(name, flash) {
+            Some(entry) => (entry.family, entry.line, entry.info),
+            None => return Err(ErrorKind::UnknownMcu(name.to_string()).into()),
+        };
+
+        if *pinout.package() != info.package {
+            return Err(ErrorKind::InvalidPinout(format!(
+                "package {} doesn't match {} of {}",
+                pinout.package(),
+                info.package,
+                name
+            ))
+            .into());
+        }
+
+        let frequency = match info.frequency {
+            Some(frequency) => frequency,
+            None => return Err(ErrorKind::UnknownFrequency(name.to_string()).into()),
+        };
+
+        let mut ips: Vec<IP> = info.ips
+            .iter()
+            .map(|ip| IP {
+                config_file: String::new(),
+                name: ip.ip_type.clone(),
+            })
+            .collect();
+
+        ips.push(IP {
+            config_file: gpio.name().to_owned(),
+            name: String::from("GPIO"),
+        });
+
+        let mut pins = Pins {
+            pins: pinout
+                .pins()
+                .iter()
+                .map(|pin| {
+                    let mut builder = PinBuilder::new(&pin.pin_type, pin.position, &pin.name);
+
+                    if pin.pin_type != "I/O" {
+                        return builder.finish();
+                    }
+
+                    let mut signals: Vec<String> = match gpio.find_pin(&pin.name) {
+                        Some(modes) => modes.signals.iter().map(|s| s.name.clone()).collect(),
+                        None => vec![],
+                    };
+                    signals.push(String::from("GPIO"));
+
+                    builder.reset_signals(signals).finish()
+                })
+                .collect(),
+        };
+
+        gpio.apply(&mut pins);
+
+        Ok(MCU {
+            memory: info.memory.clone(),
+            frequency: frequency,
+            core: info.core.clone(),
+            name: info.name.clone(),
+            package: info.package.clone(),
+            ips: ips,
+            pins: pins.pins,
+            platform: Platform::STM32 {
+                family: family.name.clone(),
+                line: line.name.clone(),
+            },
+        })
+    }
+}
+
+fn parse_core(core: &str) -> Result<Core> {
+    match core {
+        "ARM Cortex-M0" => Ok(Core::ARM(ARMCore::CortexM0)),
+        "ARM Cortex-M0+" => Ok(Core::ARM(ARMCore::CortexM0Plus)),
+        "ARM Cortex-M3" => Ok(Core::ARM(ARMCore::CortexM3)),
+        "ARM Cortex-M4" => Ok(Core::ARM(ARMCore::CortexM4)),
+        "ARM Cortex-M7" => Ok(Core::ARM(ARMCore::CortexM7)),
+        core => Err(ErrorKind::UnknownCore(core.to_string()).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use memory::SectorGroup;
+
+    #[test]
+    fn load_database() {
+        let sample = Path::new("./samples/mcus.json");
+        let db = McuDatabase::new(sample).unwrap();
+
+        assert_eq!(9, db.families().len());
+        assert_eq!(964, db.mcus().len());
+
+        let family = db.find_family("STM32F0").unwrap();
+        assert_eq!(4, family.lines.len());
+        assert_eq!("STM32F0x0 Value Line", family.lines[0].name);
+    }
+
+    #[test]
+    fn find_mcu_ok() {
+        let sample = Path::new("./samples/mcus.json");
+        let db = McuDatabase::new(sample).unwrap();
+
+        let entry = db.find_mcu("STM32F030C6Tx", 32768).unwrap();
+        let (family, line, mcu) = (entry.family, entry.line, entry.info);
+
+        assert_eq!(family.name, "STM32F0");
+        assert_eq!(line.name, "STM32F0x0 Value Line");
+        assert_eq!(mcu.core, Core::ARM(ARMCore::CortexM0));
+        assert_eq!(mcu.frequency, Some(Frequency::from_mhz(48)));
+        assert_eq!(mcu.package, Package::LQFP(48));
+        assert_eq!(mcu.ios, 39);
+        assert_eq!(
+            mcu.memory,
+            vec![
+                Memory::Flash {
+                    start: 0x08000000,
+                    size: 32768,
+                    sectors: vec![SectorGroup {
+                        count: 32,
+                        size: 1024,
+                    }],
+                },
+                Memory::Ram {
+                    start: 0x20000000,
+                    size: 4096,
+                },
+            ]
+        );
+        assert_eq!(mcu.flash_size(), 32768);
+        assert_eq!(mcu.ram_size(), 4096);
+        assert_eq!(mcu.ip_count("USART"), 1);
+        assert_eq!(mcu.ip_count("CAN"), 0);
+    }
+
+    #[test]
+    fn find_mcu_eeprom_no_frequency() {
+        let sample = Path::new("./samples/mcus.json");
+        let db = McuDatabase::new(sample).unwrap();
+
+        let mcu = db.find_mcu("STM32L073V8Ix", 65536).unwrap().info;
+
+        assert_eq!(mcu.core, Core::ARM(ARMCore::CortexM0Plus));
+        assert_eq!(mcu.frequency, None);
+        assert_eq!(
+            mcu.memory[2],
+            Memory::Eeprom {
+                start: 0x08080000,
+                size: 3072,
+            }
+        );
+    }
+
+    #[test]
+    fn mcu_frequency_out_of_range() {
+        let raw = |frequency: u16| -> RawMcu {
+            serde_json::from_str(&format!(
+                r#"{{"Core": "ARM Cortex-M0", "Eeprom": 0, "Flash": 32, "Frequency": {},
+                    "IOs": 39, "Name": "STM32F030C6Tx", "Package": "LQFP48", "Ram": 4,
+                    "IP": []}}"#,
+                frequency
+            ))
+            .unwrap()
+        };
+
+        let mcu = McuInfo::from_raw(raw(48), "STM32F0").unwrap();
+        assert_eq!(mcu.frequency, Some(Frequency::from_mhz(48)));
+
+        match McuInfo::from_raw(raw(5000), "STM32F0") {
+            Err(Error(ErrorKind::InvalidFrequency(value), _)) => assert_eq!(value, "5000MHz"),
+            _ => panic!("5 GHz doesn't fit the frequency"),
+        }
+    }
+
+    #[test]
+    fn find_mcu_second_ram() {
+        let sample = Path::new("./samples/mcus.json");
+        let db = McuDatabase::new(sample).unwrap();
+
+        let f407 = db.find_mcu("STM32F407V(E-G)Tx", 1024 * 1024).unwrap().info;
+        assert_eq!(
+            f407.memory[1..],
+            [
+                Memory::Ram {
+                    start: 0x20000000,
+                    size: 128 * 1024,
+                },
+                Memory::Ccm {
+                    start: 0x10000000,
+                    size: 64 * 1024,
+                },
+            ]
+        );
+        assert_eq!(f407.ram_size(), 192 * 1024);
+
+        let l476 = db.find_mcus("STM32L476R(C-E-G)Tx")[0].info;
+        assert_eq!(
+            l476.memory[2],
+            Memory::Sram {
+                name: "SRAM2".to_string(),
+                start: 0x10000000,
+                size: 32 * 1024,
+            }
+        );
+
+        let f446 = db.find_mcu("STM32F446R(C-E)Tx", 256 * 1024).unwrap().info;
+        assert_eq!(f446.memory.len(), 2);
+    }
+
+    #[test]
+    fn find_mcu_missing() {
+        let sample = Path::new("./samples/mcus.json");
+        let db = McuDatabase::new(sample).unwrap();
+
+        assert!(db.find_mcu("XXXX", 32768).is_none());
+        assert!(db.find_mcu("STM32F030C6Tx", 65536).is_none());
+    }
+
+    #[test]
+    fn find_mcu_flash_variants() {
+        let sample = Path::new("./samples/mcus.json");
+        let db = McuDatabase::new(sample).unwrap();
+
+        let variants: Vec<u32> = db
+            .find_mcus("STM32F407V(E-G)Tx")
+            .iter()
+            .map(|entry| entry.info.flash_size())
+            .collect();
+        assert_eq!(variants, vec![512 * 1024, 1024 * 1024]);
+
+        let entry = db.find_mcu("STM32F407V(E-G)Tx", 1024 * 1024).unwrap();
+        assert_eq!(entry.line.name, "STM32F407/417");
+        assert_eq!(entry.info.flash_size(), 1024 * 1024);
+    }
+
+    #[test]
+    fn build_mcu_ok() {
+        let sample = Path::new("./samples/mcus.json");
+        let db = McuDatabase::new(sample).unwrap();
+        let sample = Path::new("./samples/GPIO-STM32F446_gpio_v1_0_Modes.json");
+        let gpio = GpioModes::new(sample).unwrap();
+
+        let sample = Path::new("./samples/STM32F446RCTx.json");
+        let pinout = McuPinout::new(sample).unwrap();
+
+        let mcu = db
+            .build_mcu("STM32F446R(C-E)Tx", 256 * 1024, &pinout, &gpio)
+            .unwrap();
+
+        assert_eq!(mcu.core, Core::ARM(ARMCore::CortexM4));
+        assert_eq!(mcu.frequency, Frequency::from_mhz(180));
+        assert_eq!(mcu.package, Package::LQFP(64));
+        assert_eq!(
+            mcu.memory,
+            vec![
+                Memory::Flash {
+                    start: 0x08000000,
+                    size: 262144,
+                    sectors: flash_sectors("STM32F4", 262144),
+                },
+                Memory::Ram {
+                    start: 0x20000000,
+                    size: 131072,
+                },
+            ]
+        );
+        assert_eq!(mcu.ips.len(), 21);
+        assert_eq!(mcu.ips[20].config_file, "GPIO-STM32F446_gpio_v1_0_Modes");
+        assert_eq!(mcu.pins.len(), 64);
+
+        let pin = &mcu.pins[0];
+        assert_eq!(pin.name(), "VBAT");
+        assert_eq!(pin.pin_type(), "Power");
+        assert_eq!(*pin.position(), Position::Linear(1));
+
+        let pin = mcu.pins.iter().find(|pin| pin.name() == "PA10").unwrap();
+        assert_eq!(*pin.position(), Position::Linear(43));
+        assert_eq!(
+            *pin.params().unwrap().signals(),
+            vec![
+                "DCMI_D1".to_string(),
+                "TIM1_CH3".to_string(),
+                "USART1_RX".to_string(),
+                "USB_OTG_FS_ID".to_string(),
+                "GPIO".to_string(),
+            ]
+        );
+        assert_eq!(pin.params().unwrap().signal_af("USART1_RX"), Some(7));
+
+        match mcu.platform {
+            Platform::STM32 {
+                ref family,
+                ref line,
+            } => {
+                assert_eq!(family, "STM32F4");
+                assert_eq!(line, "STM32F446");
+            }
+            _ => assert!(false),
+        }
+
+        let mcu_conf = mcu.finish();
+        assert_eq!(mcu_conf.get_name(), "STM32F446R(C-E)Tx");
+    }
+
+    #[test]
+    fn build_mcu_missing() {
+        let sample = Path::new("./samples/mcus.json");
+        let db = McuDatabase::new(sample).unwrap();
+        let sample = Path::new("./samples/GPIO-STM32F446_gpio_v1_0_Modes.json");
+        let gpio = GpioModes::new(sample).unwrap();
+
+        let sample = Path::new("./samples/STM32F446RCTx.json");
+        let pinout = McuPinout::new(sample).unwrap();
+
+        assert!(db.build_mcu("XXXX", 32768, &pinout, &gpio).is_err());
+        assert!(db
+            .build_mcu("STM32F446R(C-E)Tx", 1024 * 1024, &pinout, &gpio)
+            .is_err());
+    }
+
+    #[test]
+    fn build_mcu_invalid_pinout() {
+        let sample = Path::new("./samples/mcus.json");
+        let db = McuDatabase::new(sample).unwrap();
+        let sample = Path::new("./samples/GPIO-STM32F446_gpio_v1_0_Modes.json");
+        let gpio = GpioModes::new(sample).unwrap();
+        let sample = Path::new("./samples/STM32F446RCTx.json");
+        let pinout = McuPinout::new(sample).unwrap();
+
+        // LQFP100 part with the LQFP64 pinout
+        match db.build_mcu("STM32F446V(C-E)Tx", 256 * 1024, &pinout, &gpio) {
+            Err(Error(ErrorKind::InvalidPinout(_), _)) => (),
+            _ => panic!("pinout of another package was accepted"),
+        }
+    }
+
+    #[test]
+    fn build_mcu_no_frequency() {
+        let sample = Path::new("./samples/mcus.json");
+        let db = McuDatabase::new(sample).unwrap();
+        let sample = Path::new("./samples/GPIO-STM32F446_gpio_v1_0_Modes.json");
+        let gpio = GpioModes::new(sample).unwrap();
+        let sample = Path::new("./samples/STM32F446RCTx.json");
+        let mut pinout = McuPinout::new(sample).unwrap();
+
+        let entry = db.find_mcus("STM32L073V8Ix")[0];
+        let flash = entry.info.flash_size();
+        pinout.package = entry.info.package.clone();
+
+        match db.build_mcu("STM32L073V8Ix", flash, &pinout, &gpio) {
+            Err(Error(ErrorKind::UnknownFrequency(name), _)) => {
+                assert_eq!(name, "STM32L073V8Ix")
+            }
+            _ => panic!("missing frequency was accepted"),
+        }
+    }
+
+    #[test]
+    fn unknown_core() {
+        assert!(parse_core("ARM Cortex-M33").is_err());
+    }
+}
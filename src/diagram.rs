@@ -0,0 +1,423 @@
+use std::fmt::Write;
+
+use geometry::{row_label, Corner, Geometry, Layout, Side};
+use package::Package;
+use pin::{Pin, Position};
+use pins::Pins;
+use signal::SignalKind;
+
+// Distances of the drawing in SVG user units
+const PITCH: i32 = 20;
+const PIN_LENGTH: i32 = 24;
+const PIN_WIDTH: i32 = 10;
+const BALL_PITCH: i32 = 32;
+const BALL_RADIUS: i32 = 11;
+const MARGIN: i32 = 160;
+
+// PackageDiagram class
+//
+//    Renders the package as SVG. Quad-flat packages get pins on four sides, TSSOP on
+//    two sides and BGA/WLCSP a grid of balls with row letters. Pins are coloured by
+//    type and by kind of the selected signal.
+//
+//    \sa Package, Geometry, Pins
+#[derive(Debug)]
+pub struct PackageDiagram<'a> {
+    package: &'a Package,
+    geometry: Geometry,
+    pins: &'a Pins,
+}
+
+impl<'a> PackageDiagram<'a> {
+    pub fn new(package: &'a Package, pins: &'a Pins) -> PackageDiagram<'a> {
+        PackageDiagram {
+            package: package,
+            geometry: Geometry::with_pins(package, pins.pins()),
+            pins: pins,
+        }
+    }
+
+    pub fn svg(&self) -> String {
+        match self.geometry.layout() {
+            Layout::Grid { rows, columns } => self.grid_svg(i32::from(rows), i32::from(columns)),
+            Layout::Quad { .. } | Layout::Dual { .. } => self.linear_svg(),
+        }
+    }
+
+    fn linear_svg(&self) -> String {
+        let per_side = i32::from(self.geometry.pins_per_side());
+
+        let height = (per_side + 1) * PITCH;
+        let width = match self.geometry.layout() {
+            Layout::Quad { .. } => height,
+            _ => 8 * PITCH,
+        };
+
+        let mut out = String::new();
+        write_header(&mut out, width + 2 * MARGIN, height + 2 * MARGIN);
+
+        writeln!(
+            out,
+            "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#333333\"/>",
+            MARGIN, MARGIN, width, height
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "  <circle cx=\"{}\" cy=\"{}\" r=\"4\" fill=\"#cccccc\"/>",
+            MARGIN + PITCH / 2,
+            MARGIN + PITCH / 2
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "  <text x=\"{}\" y=\"{}\" text-anchor=\"middle\" fill=\"#ffffff\">{}{}</text>",
+            MARGIN + width / 2,
+            MARGIN + height / 2,
+            self.package.family(),
+            self.package.pins()
+        )
+        .unwrap();
+
+        for pin in self.pins.pins() {
+            let (side, offset) = match *pin.position() {
+                Position::Linear(number) => match self.geometry.side_of(number) {
+                    Some((side, offset)) => (side, i32::from(offset)),
+                    None => continue,
+                },
+                Position::Grid(..) => continue,
+            };
+
+            // Pin center along the side and the body edge it sticks out of
+            let (x, y, rotation, anchor) = match side {
+                Side::Left => (MARGIN, MARGIN + (offset + 1) * PITCH, 0, "end"),
+                Side::Right => (
+                    MARGIN + width,
+                    MARGIN + height - (offset + 1) * PITCH,
+                    0,
+                    "start",
+                ),
+                Side::Bottom => (MARGIN + (offset + 1) * PITCH, MARGIN + height, 90, "start"),
+                Side::Top => (MARGIN + width - (offset + 1) * PITCH, MARGIN, -90, "start"),
+            };
+
+            let (rx, ry, rw, rh, tx, ty) = match side {
+                Side::Left => (
+                    x - PIN_LENGTH,
+                    y - PIN_WIDTH / 2,
+                    PIN_LENGTH,
+                    PIN_WIDTH,
+                    x - PIN_LENGTH - 4,
+                    y + 4,
+                ),
+                Side::Right => (
+                    x,
+                    y - PIN_WIDTH / 2,
+                    PIN_LENGTH,
+                    PIN_WIDTH,
+                    x + PIN_LENGTH + 4,
+                    y + 4,
+                ),
+                Side::Bottom => (
+                    x - PIN_WIDTH / 2,
+                    y,
+                    PIN_WIDTH,
+                    PIN_LENGTH,
+                    x - 4,
+                    y + PIN_LENGTH + 4,
+                ),
+                Side::Top => (
+                    x - PIN_WIDTH / 2,
+                    y - PIN_LENGTH,
+                    PIN_WIDTH,
+                    PIN_LENGTH,
+                    x + 4,
+                    y - PIN_LENGTH - 4,
+                ),
+            };
+
+            writeln!(out, "  <g>").unwrap();
+            writeln!(out, "    <title>{}</title>", escape(&title(pin))).unwrap();
+            writeln!(
+                out,
+                "    <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                rx,
+                ry,
+                rw,
+                rh,
+                colour(pin)
+            )
+            .unwrap();
+
+            let transform = match rotation {
+                0 => String::new(),
+                angle => format!(" transform=\"rotate({} {} {})\"", angle, tx, ty),
+            };
+
+            writeln!(
+                out,
+                "    <text x=\"{}\" y=\"{}\" text-anchor=\"{}\"{}>{}</text>",
+                tx,
+                ty,
+                anchor,
+                transform,
+                escape(&text(pin))
+            )
+            .unwrap();
+            writeln!(out, "  </g>").unwrap();
+        }
+
+        writeln!(out, "</svg>").unwrap();
+
+        out
+    }
+
+    fn grid_svg(&self, rows: i32, columns: i32) -> String {
+        let width = (columns + 1) * BALL_PITCH;
+        let height = (rows + 1) * BALL_PITCH;
+        let margin = 2 * BALL_PITCH;
+
+        // Ball centers of row and column counted from 1, A1 sits in the pin 1 corner
+        let pin1 = self.geometry.pin1();
+        let x = |column: i32| match pin1 {
+            Corner::TopLeft | Corner::BottomLeft => margin + column * BALL_PITCH,
+            Corner::TopRight | Corner::BottomRight => margin + (columns + 1 - column) * BALL_PITCH,
+        };
+        let y = |row: i32| match pin1 {
+            Corner::TopLeft | Corner::TopRight => margin + row * BALL_PITCH,
+            Corner::BottomLeft | Corner::BottomRight => margin + (rows + 1 - row) * BALL_PITCH,
+        };
+
+        let mut out = String::new();
+        write_header(&mut out, width + 2 * margin, height + 2 * margin);
+
+        writeln!(
+            out,
+            "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#333333\"/>",
+            margin, margin, width, height
+        )
+        .unwrap();
+
+        for column in 1..=columns {
+            writeln!(
+                out,
+                "  <text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+                x(column),
+                margin - 8,
+                column
+            )
+            .unwrap();
+        }
+
+        for row in 1..=rows {
+            writeln!(
+                out,
+                "  <text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>",
+                margin - 8,
+                y(row) + 4,
+                row_label(row as u8)
+            )
+            .unwrap();
+        }
+
+        for pin in self.pins.pins() {
+            let (row, column) = match *pin.position() {
+                Position::Grid(row, column) => match self.geometry.ball_name(row, column) {
+                    Some(_) => (i32::from(row), i32::from(column)),
+                    None => continue,
+                },
+                Position::Linear(_) => continue,
+            };
+
+            writeln!(out, "  <g>").unwrap();
+            writeln!(
+                out,
+                "    <title>{} {}</title>",
+                pin.position(),
+                escape(&title(pin))
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "    <circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"/>",
+                x(column),
+                y(row),
+                BALL_RADIUS,
+                colour(pin)
+            )
+            .unwrap();
+            writeln!(out, "  </g>").unwrap();
+        }
+
+        writeln!(out, "</svg>").unwrap();
+
+        out
+    }
+}
+
+fn write_header(out: &mut String, width: i32, height: i32) {
+    writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" font-family=\"monospace\" font-size=\"10\">",
+        width, height, width, height
+    )
+    .unwrap();
+}
+
+// Power, reset and boot pins by type, I/O by the selected signal
+fn colour(pin: &Pin) -> &'static str {
+    match *pin {
+        Pin::NC { .. } => "#bbbbbb",
+        Pin::POWER { .. } => "#d9534f",
+        Pin::NRST { .. } | Pin::BOOT { .. } => "#f0ad4e",
+        Pin::IO { ref params, .. } => match params.current().map(|s| s.kind()) {
+            None => "#e8e8e8",
+            Some(SignalKind::Gpio) => "#5cb85c",
+            Some(SignalKind::AlternateFunction) => "#428bca",
+            Some(SignalKind::Analog) => "#9b59b6",
+            Some(SignalKind::Exti) => "#f7e463",
+        },
+    }
+}
+
+// Pin name followed by the user label or selected signal
+fn text(pin: &Pin) -> String {
+    let detail = match pin.params() {
+        Some(params) if !params.label().is_empty() => Some(params.label()),
+        Some(params) => params.current_signal(),
+        None => None,
+    };
+
+    match detail {
+        Some(detail) => format!("{} {}", pin.name(), detail),
+        None => pin.name().to_string(),
+    }
+}
+
+fn title(pin: &Pin) -> String {
+    match pin.params().and_then(|p| p.current_signal()) {
+        Some(signal) => format!("{} ({}) {}", pin.name(), pin.pin_type(), signal),
+        None => format!("{} ({})", pin.name(), pin.pin_type()),
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use mcu::MCU;
+    use pin::PinBuilder;
+    use std::path::Path;
+
+    #[test]
+    fn quad_flat() {
+        let sample = Path::new("./samples/STM32F030C6Tx.json");
+        let mut mcu_conf = MCU::new(sample).unwrap().finish();
+
+        {
+            let pins = mcu_conf.get_pins_mut();
+            let idx = pins.find_pin("PA9")[0];
+            let params = pins.pins[idx].params_mut().unwrap();
+            params.select_signal("USART1_TX");
+            params.set_label("<TX>");
+        }
+
+        let svg = PackageDiagram::new(mcu_conf.get_package(), mcu_conf.get_pins()).svg();
+
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.ends_with("</svg>\n"));
+        assert!(svg.contains(">LQFP48</text>"));
+        assert!(svg.contains("<title>PA9 (I/O) USART1_TX</title>"));
+        assert!(svg.contains(">PA9 &lt;TX&gt;</text>"));
+        assert!(svg.contains("fill=\"#428bca\""));
+
+        // 12 pins per side, pin 13 is the first one of the bottom side
+        assert!(svg.contains(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"",
+            MARGIN + PITCH - PIN_WIDTH / 2,
+            MARGIN + 13 * PITCH,
+            PIN_WIDTH,
+            PIN_LENGTH
+        )));
+    }
+
+    #[test]
+    fn two_sides() {
+        let pins = Pins {
+            pins: vec![
+                PinBuilder::new("Power", Position::Linear(1), "VDD").finish(),
+                PinBuilder::new("Power", Position::Linear(20), "VSS").finish(),
+            ],
+        };
+
+        let svg = PackageDiagram::new(&Package::TSSOP(20), &pins).svg();
+
+        // Pin 20 is the top one of the right side
+        assert!(svg.contains(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#d9534f\"/>",
+            MARGIN + 8 * PITCH,
+            MARGIN + PITCH - PIN_WIDTH / 2,
+            PIN_LENGTH,
+            PIN_WIDTH
+        )));
+        assert_eq!(svg.matches("<g>").count(), 2);
+    }
+
+    #[test]
+    fn grid() {
+        let pins = Pins {
+            pins: vec![
+                PinBuilder::new("I/O", Position::Grid(1, 1), "PA0")
+                    .signals(vec![String::from("ADC_IN0"), String::from("GPIO")], 0)
+                    .finish(),
+                PinBuilder::new("Power", Position::Grid(9, 9), "VDD").finish(),
+            ],
+        };
+
+        let svg = PackageDiagram::new(&Package::UFBGA(81), &pins).svg();
+
+        assert!(svg.contains("<title>A1 PA0 (I/O) ADC_IN0</title>"));
+        assert!(svg.contains("<title>J9 VDD (Power)</title>"));
+        assert!(svg.contains(">H</text>"));
+        assert!(!svg.contains(">I</text>"));
+        assert!(svg.contains(">J</text>"));
+        assert!(svg.contains("fill=\"#9b59b6\""));
+    }
+
+    #[test]
+    fn grid_ufbga176() {
+        let pins = Pins {
+            pins: vec![PinBuilder::new("Power", Position::Grid(15, 15), "VSS").finish()],
+        };
+
+        let svg = PackageDiagram::new(&Package::UFBGA(176), &pins).svg();
+
+        assert!(svg.contains("<title>R15 VSS (Power)</title>"));
+        assert!(svg.contains(">R</text>"));
+    }
+
+    #[test]
+    fn grid_pin1_right() {
+        let pins = Pins {
+            pins: vec![PinBuilder::new("Power", Position::Grid(1, 1), "VDD").finish()],
+        };
+
+        let svg = PackageDiagram::new(&Package::WLCSP(49), &pins).svg();
+        let margin = 2 * BALL_PITCH;
+
+        // A1 is the top right ball
+        assert!(svg.contains(&format!(
+            "<circle cx=\"{}\" cy=\"{}\"",
+            margin + 7 * BALL_PITCH,
+            margin + BALL_PITCH
+        )));
+    }
+}
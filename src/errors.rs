@@ -4,4 +4,135 @@ error_chain!{
             SerdeJSON(::serde_json::Error);
             Parse(::std::num::ParseIntError);
         }
+
+        errors {
+            UnknownCore(core: String) {
+                description("unknown core")
+                display("unknown core: '{}'", core)
+            }
+            UnknownMcu(name: String) {
+                description("unknown MCU")
+                display("unknown MCU: '{}'", name)
+            }
+            InvalidInterrupt(value: String) {
+                description("invalid interrupt")
+                display("invalid interrupt: '{}'", value)
+            }
+            InterruptNotEnableable(irqn: String) {
+                description("interrupt can't be enabled")
+                display("interrupt can't be enabled: '{}'", irqn)
+            }
+            InterruptNotEnabled(irqn: String) {
+                description("interrupt not enabled")
+                display("interrupt not enabled: '{}'", irqn)
+            }
+            InvalidPriority(irqn: String, preemption: u8, sub_priority: u8) {
+                description("invalid interrupt priority")
+                display("invalid priority of '{}': preemption {}, sub-priority {}", irqn, preemption, sub_priority)
+            }
+            InvalidPriorityBits(priority_bits: u8) {
+                description("invalid priority bits")
+                display("invalid number of priority bits: {}", priority_bits)
+            }
+            InvalidPriorityGrouping(preemption_bits: u8) {
+                description("invalid priority grouping")
+                display("invalid priority grouping: {} preemption bits", preemption_bits)
+            }
+            MemoryOverflow(what: String, size: u32) {
+                description("memory overflow")
+                display("{} of {} bytes doesn't fit into memory", what, size)
+            }
+            MemoryOverlap(first: String, second: String) {
+                description("memory overlap")
+                display("{} overlaps {}", first, second)
+            }
+            MemoryRegionOverflow(name: String) {
+                description("memory region overflow")
+                display("{} reaches past the end of address space", name)
+            }
+            EmptyMemoryRegion(name: String) {
+                description("empty memory region")
+                display("{} has zero size", name)
+            }
+            MemorySizeMismatch(name: String, expected: u32, actual: u32) {
+                description("memory size mismatch")
+                display("{} size {} bytes differs from {} bytes in catalogue", name, actual, expected)
+            }
+            UnknownClockTree(family: String) {
+                description("unknown clock tree")
+                display("no clock tree model for '{}'", family)
+            }
+            UnsupportedClockTree(family: String) {
+                description("unsupported clock tree")
+                display("clock tree of '{}' isn't supported yet", family)
+            }
+            ClockOutOfRange(clock: String, freq: ::frequency::Frequency) {
+                description("clock out of range")
+                display("{} clock of {} is out of range", clock, freq)
+            }
+            InvalidFrequency(value: String) {
+                description("invalid frequency")
+                display("invalid frequency: '{}'", value)
+            }
+            NoClockSolution(sysclk: ::frequency::Frequency) {
+                description("no clock configuration")
+                display("no clock configuration gives SYSCLK of {}", sysclk)
+            }
+            UnknownPeripheral(name: String) {
+                description("unknown peripheral")
+                display("unknown peripheral: '{}'", name)
+            }
+            NoClockConfiguration {
+                description("no clock configuration")
+                display("clocks aren't configured")
+            }
+            NoPinAvailable(signal: String) {
+                description("no pin available")
+                display("no pin available for signal '{}'", signal)
+            }
+            PinLocked {
+                description("pin locked")
+                display("pin is locked")
+            }
+            PinConflicts(report: String) {
+                description("pin conflicts")
+                display("pin conflicts:\n{}", report)
+            }
+            InvalidGpioConfig(reason: String) {
+                description("invalid GPIO configuration")
+                display("invalid GPIO configuration: {}", reason)
+            }
+            InvalidNetlist(reason: String) {
+                description("invalid netlist")
+                display("invalid netlist: {}", reason)
+            }
+            InvalidPackage(package: String) {
+                description("invalid package")
+                display("invalid package: '{}'", package)
+            }
+            UnknownPackage(family: String) {
+                description("unknown package")
+                display("unknown package family: '{}'", family)
+            }
+            InvalidPosition(position: String) {
+                description("invalid pin position")
+                display("invalid pin position: '{}'", position)
+            }
+            InvalidPinout(reason: String) {
+                description("invalid pinout")
+                display("invalid pinout: {}", reason)
+            }
+            UnknownFrequency(name: String) {
+                description("unknown frequency")
+                display("catalogue has no frequency for '{}'", name)
+            }
+            UnknownFlashGeometry {
+                description("unknown flash geometry")
+                display("flash has no sector table")
+            }
+            UnknownPartition(name: String) {
+                description("unknown flash partition")
+                display("unknown flash partition: '{}'", name)
+            }
+        }
     }
\ No newline at end of file
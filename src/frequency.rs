@@ -0,0 +1,311 @@
+use std::fmt;
+use std::ops::{Add, Div, Mul, Sub};
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use errors::*;
+
+const UNITS: [(&str, u32); 4] = [
+    ("GHz", 1_000_000_000),
+    ("MHz", 1_000_000),
+    ("kHz", 1_000),
+    ("Hz", 1),
+];
+
+// Frequency class
+//
+//    Frequency with Hz precision. Parses and displays values with units such as "8MHz"
+//    or "32.768kHz", JSON keeps the {"MHz": 48} form for whole MHz.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
+pub struct Frequency(u32);
+
+impl Frequency {
+    pub fn from_hz(hz: u32) -> Frequency {
+        Frequency(hz)
+    }
+
+    // Panics past u32::MAX Hz, use checked_from_khz() for untrusted values
+    pub fn from_khz(khz: u32) -> Frequency {
+        Frequency::checked_from_khz(khz).expect("frequency overflow")
+    }
+
+    // Panics past u32::MAX Hz, use checked_from_mhz() for untrusted values
+    pub fn from_mhz(mhz: u32) -> Frequency {
+        Frequency::checked_from_mhz(mhz).expect("frequency overflow")
+    }
+
+    pub fn checked_from_khz(khz: u32) -> Option<Frequency> {
+        khz.checked_mul(1_000).map(Frequency)
+    }
+
+    pub fn checked_from_mhz(mhz: u32) -> Option<Frequency> {
+        mhz.checked_mul(1_000_000).map(Frequency)
+    }
+
+    pub fn hz(&self) -> u32 {
+        self.0
+    }
+
+    // Whole MHz, rounded down
+    pub fn mhz(&self) -> u32 {
+        self.0 / 1_000_000
+    }
+
+    pub fn checked_add(self, other: Frequency) -> Option<Frequency> {
+        self.0.checked_add(other.0).map(Frequency)
+    }
+
+    pub fn checked_sub(self, other: Frequency) -> Option<Frequency> {
+        self.0.checked_sub(other.0).map(Frequency)
+    }
+
+    pub fn checked_mul(self, factor: u32) -> Option<Frequency> {
+        self.0.checked_mul(factor).map(Frequency)
+    }
+}
+
+// Operators panic on overflow in release builds too, the checked_* methods return None
+// instead
+
+impl Add for Frequency {
+    type Output = Frequency;
+
+    fn add(self, other: Frequency) -> Frequency {
+        self.checked_add(other).expect("frequency overflow")
+    }
+}
+
+impl Sub for Frequency {
+    type Output = Frequency;
+
+    fn sub(self, other: Frequency) -> Frequency {
+        self.checked_sub(other).expect("frequency underflow")
+    }
+}
+
+impl Mul<u32> for Frequency {
+    type Output = Frequency;
+
+    fn mul(self, factor: u32) -> Frequency {
+        self.checked_mul(factor).expect("frequency overflow")
+    }
+}
+
+impl Div<u32> for Frequency {
+    type Output = Frequency;
+
+    fn div(self, divider: u32) -> Frequency {
+        Frequency(self.0 / divider)
+    }
+}
+
+// Ratio of two frequencies such as prescaler or baud rate divider
+impl Div for Frequency {
+    type Output = u32;
+
+    fn div(self, other: Frequency) -> u32 {
+        self.0 / other.0
+    }
+}
+
+impl fmt::Display for Frequency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let &(unit, scale) = UNITS
+            .iter()
+            .find(|u| self.0 >= u.1)
+            .unwrap_or(&UNITS[UNITS.len() - 1]);
+
+        let whole = self.0 / scale;
+        let fraction = self.0 % scale;
+
+        if fraction == 0 {
+            return write!(f, "{}{}", whole, unit);
+        }
+
+        let digits = format!("{:0width$}", fraction, width = scale.to_string().len() - 1);
+        write!(f, "{}.{}{}", whole, digits.trim_end_matches('0'), unit)
+    }
+}
+
+impl FromStr for Frequency {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Frequency> {
+        let invalid = || Error::from(ErrorKind::InvalidFrequency(s.to_string()));
+
+        let s = s.trim();
+        let split = s
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(s.len());
+        let (number, unit) = (&s[..split], s[split..].trim());
+
+        // Units are case sensitive, "mHz" would be millihertz
+        let scale = match UNITS.iter().find(|u| u.0 == unit) {
+            Some(&(_, scale)) => u64::from(scale),
+            None if unit.is_empty() => 1,
+            None => return Err(invalid()),
+        };
+
+        let mut parts = number.splitn(2, '.');
+        let whole = parts.next().unwrap_or("");
+        let fraction = parts.next().unwrap_or("");
+
+        if whole.is_empty() && fraction.is_empty() {
+            return Err(invalid());
+        }
+
+        let mut hz = match whole {
+            "" => 0,
+            whole => whole.parse::<u64>().map_err(|_| invalid())? * scale,
+        };
+
+        // Fraction digits below 1 Hz are refused rather than rounded
+        let mut digit_scale = scale;
+        for c in fraction.chars() {
+            let digit = u64::from(c.to_digit(10).ok_or_else(invalid)?);
+            digit_scale /= 10;
+
+            if digit_scale == 0 && digit != 0 {
+                return Err(invalid());
+            }
+
+            hz += digit * digit_scale;
+        }
+
+        if hz > u64::from(u32::MAX) {
+            return Err(invalid());
+        }
+
+        Ok(Frequency(hz as u32))
+    }
+}
+
+// Stored as {"MHz": 48} when whole MHz to keep older readers working, {"Hz": 32768}
+// otherwise
+impl Serialize for Frequency {
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if self.0 % 1_000_000 == 0 {
+            serializer.serialize_newtype_variant("Frequency", 0, "MHz", &self.mhz())
+        } else {
+            serializer.serialize_newtype_variant("Frequency", 1, "Hz", &self.0)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+enum UnitFrequency {
+    MHz(u32),
+    #[serde(rename = "kHz")]
+    KHz(u32),
+    Hz(u32),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawFrequency {
+    Unit(UnitFrequency),
+    Text(String),
+}
+
+impl<'de> Deserialize<'de> for Frequency {
+    fn deserialize<D>(deserializer: D) -> ::std::result::Result<Frequency, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let frequency = match RawFrequency::deserialize(deserializer)? {
+            RawFrequency::Unit(UnitFrequency::MHz(mhz)) => Frequency::checked_from_mhz(mhz),
+            RawFrequency::Unit(UnitFrequency::KHz(khz)) => Frequency::checked_from_khz(khz),
+            RawFrequency::Unit(UnitFrequency::Hz(hz)) => Some(Frequency::from_hz(hz)),
+            RawFrequency::Text(text) => {
+                return text
+                    .parse()
+                    .map_err(|e: Error| ::serde::de::Error::custom(e.to_string()))
+            }
+        };
+
+        frequency.ok_or_else(|| ::serde::de::Error::custom("frequency out of range"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn parse() {
+        assert_eq!("8MHz".parse::<Frequency>().unwrap(), Frequency::from_mhz(8));
+        assert_eq!(
+            "32.768kHz".parse::<Frequency>().unwrap(),
+            Frequency::from_hz(32_768)
+        );
+        assert_eq!(
+            "1.5 MHz".parse::<Frequency>().unwrap(),
+            Frequency::from_khz(1_500)
+        );
+        assert_eq!(
+            "40000".parse::<Frequency>().unwrap(),
+            Frequency::from_khz(40)
+        );
+        assert!("0.5Hz".parse::<Frequency>().is_err());
+        assert!("8 MHzz".parse::<Frequency>().is_err());
+        assert!("8mHz".parse::<Frequency>().is_err());
+        assert!("8MHZ".parse::<Frequency>().is_err());
+        assert!("MHz".parse::<Frequency>().is_err());
+        assert!("5GHz".parse::<Frequency>().is_err());
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Frequency::from_mhz(48).to_string(), "48MHz");
+        assert_eq!(Frequency::from_hz(32_768).to_string(), "32.768kHz");
+        assert_eq!(Frequency::from_hz(180_500_000).to_string(), "180.5MHz");
+        assert_eq!(Frequency::from_hz(500).to_string(), "500Hz");
+        assert_eq!(Frequency::from_hz(0).to_string(), "0Hz");
+
+        let lse = Frequency::from_hz(32_768);
+        assert_eq!(lse.to_string().parse::<Frequency>().unwrap(), lse);
+    }
+
+    #[test]
+    fn arithmetic() {
+        let hse = Frequency::from_mhz(8);
+
+        assert_eq!(hse * 21 / 2, Frequency::from_mhz(84));
+        assert_eq!(hse + Frequency::from_mhz(4), Frequency::from_mhz(12));
+        assert_eq!(hse - Frequency::from_mhz(4), Frequency::from_mhz(4));
+        assert_eq!(Frequency::from_mhz(84) / Frequency::from_hz(115_200), 729);
+        assert!(hse < Frequency::from_mhz(16));
+
+        assert_eq!(hse.checked_sub(Frequency::from_mhz(16)), None);
+        assert_eq!(hse.checked_mul(1_000), None);
+        assert_eq!(Frequency::checked_from_mhz(5_000), None);
+        assert_eq!(
+            Frequency::checked_from_khz(32),
+            Some(Frequency::from_hz(32_000))
+        );
+    }
+
+    #[test]
+    fn serde() {
+        let frequency: Frequency = serde_json::from_str(r#"{"MHz":48}"#).unwrap();
+        assert_eq!(frequency, Frequency::from_mhz(48));
+        assert_eq!(serde_json::to_string(&frequency).unwrap(), r#"{"MHz":48}"#);
+
+        let lse = Frequency::from_hz(32_768);
+        let json = serde_json::to_string(&lse).unwrap();
+        assert_eq!(json, r#"{"Hz":32768}"#);
+        assert_eq!(serde_json::from_str::<Frequency>(&json).unwrap(), lse);
+
+        let text: Frequency = serde_json::from_str(r#""32.768kHz""#).unwrap();
+        assert_eq!(text, lse);
+        assert!(serde_json::from_str::<Frequency>(r#""fast""#).is_err());
+        assert!(serde_json::from_str::<Frequency>(r#"{"MHz":5000}"#).is_err());
+        assert!(serde_json::from_str::<Frequency>(r#"{"kHz":5000000}"#).is_err());
+    }
+}
@@ -0,0 +1,321 @@
+use package::Package;
+use pin::{Pin, Position};
+
+// Letters left out of BGA row names as they look like digits or other letters
+const SKIPPED_ROWS: [char; 6] = ['I', 'O', 'Q', 'S', 'X', 'Z'];
+
+// Ball arrays of grid packages from the datasheets as (family, balls, rows, columns,
+// corner of A1), balls missing from the array aren't bonded
+const GRIDS: [(&str, u16, u8, u8, Corner); 18] = [
+    ("TFBGA", 64, 8, 8, Corner::TopLeft),
+    ("UFBGA", 64, 8, 8, Corner::TopLeft),
+    ("LFBGA", 100, 10, 10, Corner::TopLeft),
+    ("TFBGA", 100, 10, 10, Corner::TopLeft),
+    ("UFBGA", 100, 12, 12, Corner::TopLeft),
+    ("UFBGA", 132, 12, 12, Corner::TopLeft),
+    ("LFBGA", 144, 12, 12, Corner::TopLeft),
+    ("UFBGA", 144, 12, 12, Corner::TopLeft),
+    ("UFBGA", 169, 13, 13, Corner::TopLeft),
+    ("UFBGA", 176, 15, 15, Corner::TopLeft),
+    ("TFBGA", 216, 15, 15, Corner::TopLeft),
+    // Chip scale packages are drawn with A1 on the right in the top view
+    ("WLCSP", 25, 5, 5, Corner::TopRight),
+    ("WLCSP", 36, 6, 6, Corner::TopRight),
+    ("WLCSP", 49, 7, 7, Corner::TopRight),
+    ("WLCSP", 64, 8, 8, Corner::TopRight),
+    ("WLCSP", 81, 9, 9, Corner::TopRight),
+    ("WLCSP", 100, 10, 10, Corner::TopRight),
+    ("EWLCSP", 49, 7, 7, Corner::TopRight),
+];
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum Side {
+    Left,
+    Bottom,
+    Right,
+    Top,
+}
+
+// Corner of the package marked as pin 1 or ball A1, seen from the top
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum Layout {
+    // Pins on all four sides such as LQFP and QFN
+    Quad { per_side: u16 },
+    // Pins on two opposite sides such as TSSOP
+    Dual { per_side: u16 },
+    // Balls in rows and columns such as BGA and WLCSP
+    Grid { rows: u8, columns: u8 },
+}
+
+// Geometry class
+//
+//    Placement of pins on the package seen from the top. Linear packages count pins
+//    counter-clockwise from pin 1 at the top of the left side, grid packages name balls
+//    by row letter and column number from A1 in the pin 1 corner. Arrays of grid
+//    packages come from a table, unknown ones are sized from their balls.
+//
+//    \sa Package, Position
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub struct Geometry {
+    layout: Layout,
+    pin1: Corner,
+}
+
+impl Geometry {
+    pub fn new(package: &Package) -> Geometry {
+        let pins = package.pins();
+
+        let grid = GRIDS
+            .iter()
+            .find(|g| g.0 == package.family() && g.1 == pins);
+
+        let (layout, pin1) = match grid {
+            Some(&(_, _, rows, columns, pin1)) => (
+                Layout::Grid {
+                    rows: rows,
+                    columns: columns,
+                },
+                pin1,
+            ),
+            None if package.is_grid() => {
+                // Smallest square holding the balls, with_pins() grows it to fit
+                let side = (f64::from(pins)).sqrt().ceil() as u8;
+                (
+                    Layout::Grid {
+                        rows: side,
+                        columns: side,
+                    },
+                    Corner::TopLeft,
+                )
+            }
+            None if package.is_dual() => (
+                Layout::Dual {
+                    per_side: (pins + 1) / 2,
+                },
+                Corner::TopLeft,
+            ),
+            None => (
+                Layout::Quad {
+                    per_side: (pins + 3) / 4,
+                },
+                Corner::TopLeft,
+            ),
+        };
+
+        Geometry {
+            layout: layout,
+            pin1: pin1,
+        }
+    }
+
+    // Geometry of the package with the grid grown to hold every ball of the pins
+    pub fn with_pins(package: &Package, pins: &[Pin]) -> Geometry {
+        let mut geometry = Geometry::new(package);
+
+        if let Layout::Grid {
+            ref mut rows,
+            ref mut columns,
+        } = geometry.layout
+        {
+            for pin in pins {
+                if let Position::Grid(row, column) = *pin.position() {
+                    *rows = (*rows).max(row);
+                    *columns = (*columns).max(column);
+                }
+            }
+        }
+
+        geometry
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    pub fn pin1(&self) -> Corner {
+        self.pin1
+    }
+
+    // Sides with pins in the order pins are counted, none for grid packages
+    pub fn sides(&self) -> &'static [Side] {
+        match self.layout {
+            Layout::Quad { .. } => &[Side::Left, Side::Bottom, Side::Right, Side::Top],
+            Layout::Dual { .. } => &[Side::Left, Side::Right],
+            Layout::Grid { .. } => &[],
+        }
+    }
+
+    pub fn pins_per_side(&self) -> u16 {
+        match self.layout {
+            Layout::Quad { per_side } | Layout::Dual { per_side } => per_side,
+            Layout::Grid { .. } => 0,
+        }
+    }
+
+    // Side of pin number counted from 1 and its offset along the side counted from 0 in
+    // the counting direction
+    pub fn side_of(&self, number: u16) -> Option<(Side, u16)> {
+        let per_side = self.pins_per_side();
+        let sides = self.sides();
+
+        if number == 0 || per_side == 0 {
+            return None;
+        }
+
+        let idx = number - 1;
+        sides
+            .get(usize::from(idx / per_side))
+            .map(|&side| (side, idx % per_side))
+    }
+
+    // Ball name such as "F7" of row and column counted from 1
+    pub fn ball_name(&self, row: u8, column: u8) -> Option<String> {
+        match self.layout {
+            Layout::Grid { rows, columns }
+                if row >= 1 && row <= rows && column >= 1 && column <= columns =>
+            {
+                Some(format!("{}{}", row_label(row), column))
+            }
+            _ => None,
+        }
+    }
+
+    // Pin number or ball name of the position if it fits the package
+    pub fn name(&self, position: Position) -> Option<String> {
+        match position {
+            Position::Linear(number) => self.side_of(number).map(|_| number.to_string()),
+            Position::Grid(row, column) => self.ball_name(row, column),
+        }
+    }
+}
+
+// Row letters of grid packages counted from 1, rows past the alphabet continue as
+// AA, AB, ...
+pub fn row_label(row: u8) -> String {
+    let letters: Vec<char> = (b'A'..=b'Z')
+        .map(|c| c as char)
+        .filter(|c| !SKIPPED_ROWS.contains(c))
+        .collect();
+
+    let row = usize::from(row.max(1) - 1);
+    let mut label = String::new();
+
+    if row >= letters.len() {
+        label.push(letters[row / letters.len() - 1]);
+    }
+    label.push(letters[row % letters.len()]);
+
+    label
+}
+
+// Row counted from 1 of row letters such as "F" or "AA"
+pub fn row_number(label: &str) -> Option<u8> {
+    (1..=u8::MAX).find(|&row| row_label(row) == label)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use pin::PinBuilder;
+
+    #[test]
+    fn quad() {
+        let geometry = Geometry::new(&Package::LQFP(48));
+
+        assert_eq!(geometry.layout(), Layout::Quad { per_side: 12 });
+        assert_eq!(geometry.pin1(), Corner::TopLeft);
+        assert_eq!(geometry.side_of(1), Some((Side::Left, 0)));
+        assert_eq!(geometry.side_of(12), Some((Side::Left, 11)));
+        assert_eq!(geometry.side_of(13), Some((Side::Bottom, 0)));
+        assert_eq!(geometry.side_of(48), Some((Side::Top, 11)));
+        assert_eq!(geometry.side_of(49), None);
+        assert_eq!(geometry.side_of(0), None);
+        assert_eq!(geometry.ball_name(1, 1), None);
+    }
+
+    #[test]
+    fn dual() {
+        let geometry = Geometry::new(&Package::TSSOP(20));
+
+        assert_eq!(geometry.sides(), &[Side::Left, Side::Right]);
+        assert_eq!(geometry.pins_per_side(), 10);
+        assert_eq!(geometry.side_of(11), Some((Side::Right, 0)));
+        assert_eq!(geometry.name(Position::Linear(20)), Some("20".to_string()));
+        assert_eq!(geometry.name(Position::Linear(21)), None);
+    }
+
+    #[test]
+    fn grid() {
+        let geometry = Geometry::new(&Package::UFBGA(144));
+
+        assert_eq!(
+            geometry.layout(),
+            Layout::Grid {
+                rows: 12,
+                columns: 12,
+            }
+        );
+        assert_eq!(geometry.pins_per_side(), 0);
+        assert_eq!(geometry.side_of(1), None);
+        assert_eq!(geometry.ball_name(6, 7), Some("F7".to_string()));
+        assert_eq!(geometry.ball_name(9, 1), Some("J1".to_string()));
+        assert_eq!(geometry.ball_name(12, 12), Some("M12".to_string()));
+        assert_eq!(geometry.ball_name(13, 1), None);
+        assert_eq!(geometry.name(Position::Grid(0, 1)), None);
+    }
+
+    #[test]
+    fn grid_table() {
+        assert_eq!(
+            Geometry::new(&Package::UFBGA(176)).layout(),
+            Layout::Grid {
+                rows: 15,
+                columns: 15,
+            }
+        );
+        assert_eq!(
+            Geometry::new(&Package::UFBGA(176)).ball_name(15, 15),
+            Some("R15".to_string())
+        );
+
+        let wlcsp = Geometry::new(&Package::WLCSP(49));
+        assert_eq!(wlcsp.pin1(), Corner::TopRight);
+        assert_eq!(wlcsp.ball_name(7, 7), Some("G7".to_string()));
+
+        // WLCSP143 isn't in the table, its balls size the grid
+        let pins = vec![PinBuilder::new("Power", Position::Grid(13, 11), "VDD").finish()];
+        let geometry = Geometry::with_pins(&Package::WLCSP(143), &pins);
+        assert_eq!(
+            geometry.layout(),
+            Layout::Grid {
+                rows: 13,
+                columns: 12,
+            }
+        );
+        assert_eq!(
+            Geometry::with_pins(&Package::LQFP(48), &pins).layout(),
+            Layout::Quad { per_side: 12 }
+        );
+    }
+
+    #[test]
+    fn row_labels() {
+        let labels: String = (1..21).map(row_label).collect();
+
+        assert_eq!(labels, "ABCDEFGHJKLMNPRTUVWY");
+        assert_eq!(row_label(21), "AA");
+        assert_eq!(row_label(22), "AB");
+        assert_eq!(row_number("J"), Some(9));
+        assert_eq!(row_number("AB"), Some(22));
+        assert_eq!(row_number("I"), None);
+    }
+}
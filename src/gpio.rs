@@ -0,0 +1,418 @@
+use std::fs::File;
+use std::path::Path;
+
+use serde_json;
+
+use errors::*;
+use pins::Pins;
+use signal::SignalKind;
+
+// Signal is stored as single object when the pin has only one signal
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+#[derive(Deserialize, Debug)]
+struct RawModes {
+    #[serde(rename = "GPIO")] gpio: RawGpio,
+}
+
+#[derive(Deserialize, Debug)]
+struct RawGpio {
+    #[serde(rename = "Pin")] pins: Vec<RawPin>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RawPin {
+    #[serde(rename = "Name")] name: String,
+    #[serde(rename = "Signal", default)] signals: Option<OneOrMany<GpioSignal>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct GpioSignal {
+    #[serde(rename = "Name")] pub name: String,
+    #[serde(rename = "Value")] pub value: String,
+}
+
+impl GpioSignal {
+    // Alternate function number parsed from value such as "AF7"
+    pub fn af(&self) -> Option<u8> {
+        if self.value.starts_with("AF") {
+            self.value[2..].parse::<u8>().ok()
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GpioPin {
+    pub name: String,
+    pub signals: Vec<GpioSignal>,
+}
+
+// GpioModes class
+//
+//    Holds the GPIO IP mode file listing pins together with the signals they can be
+//    connected to.
+//
+//    \sa Pins, IOPin
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GpioModes {
+    name: String,
+    pins: Vec<GpioPin>,
+}
+
+impl GpioModes {
+    pub fn new(path: &Path) -> Result<GpioModes> {
+        let file = File::open(path)?;
+        let raw: RawModes = serde_json::from_reader(file)?;
+
+        let pins = raw.gpio
+            .pins
+            .into_iter()
+            .map(|pin| GpioPin {
+                name: pin.name,
+                signals: match pin.signals {
+                    Some(OneOrMany::One(signal)) => vec![signal],
+                    Some(OneOrMany::Many(signals)) => signals,
+                    None => Vec::new(),
+                },
+            })
+            .collect();
+
+        let name = match path.file_stem() {
+            Some(stem) => stem.to_string_lossy().into_owned(),
+            None => String::new(),
+        };
+
+        Ok(GpioModes {
+            name: name,
+            pins: pins,
+        })
+    }
+
+    // Name of the mode file used as IP config file
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn pins(&self) -> &Vec<GpioPin> {
+        &self.pins
+    }
+
+    // Finds pin either by full name or by port pin name, "PA0" matches "PA0-WKUP"
+    pub fn find_pin(&self, name: &str) -> Option<&GpioPin> {
+        match self.pins.iter().find(|pin| pin.name == name) {
+            Some(pin) => Some(pin),
+            None => self.pins
+                .iter()
+                .find(|pin| port_name(&pin.name) == port_name(name)),
+        }
+    }
+
+    // Sets alternate function numbers on signals of the pins, returns number of signals updated
+    pub fn apply(&self, pins: &mut Pins) -> usize {
+        let mut updated = 0;
+
+        for pin in &mut pins.pins {
+            let modes = match self.find_pin(pin.name()) {
+                Some(modes) => modes,
+                None => continue,
+            };
+
+            if let Some(params) = pin.params_mut() {
+                for signal in &modes.signals {
+                    if let Some(af) = signal.af() {
+                        if params.set_signal_af(&signal.name, af) {
+                            updated += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        updated
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum Mode {
+    Input,
+    Output,
+    Analog,
+    AlternateFunction,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum OutputType {
+    PushPull,
+    OpenDrain,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum Pull {
+    None,
+    Up,
+    Down,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum Speed {
+    Low,
+    Medium,
+    High,
+    VeryHigh,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum Level {
+    Low,
+    High,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum Edge {
+    Rising,
+    Falling,
+    Both,
+}
+
+// GpioConfig class
+//
+//    Electrical setup of a pin used by the generated init code. Default is the analog
+//    reset state with everything else off.
+//
+//    \sa IOPin, SignalKind
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub struct GpioConfig {
+    pub mode: Mode,
+    pub output_type: OutputType,
+    pub pull: Pull,
+    pub speed: Speed,
+    pub level: Level,
+    pub edge: Option<Edge>,
+}
+
+impl Default for GpioConfig {
+    fn default() -> GpioConfig {
+        GpioConfig {
+            mode: Mode::Analog,
+            output_type: OutputType::PushPull,
+            pull: Pull::None,
+            speed: Speed::Low,
+            level: Level::Low,
+            edge: None,
+        }
+    }
+}
+
+impl GpioConfig {
+    // Configuration a newly selected signal starts with
+    pub fn for_signal(kind: SignalKind) -> GpioConfig {
+        let mut config = GpioConfig::default();
+
+        match kind {
+            SignalKind::Gpio => config.mode = Mode::Input,
+            SignalKind::Exti => {
+                config.mode = Mode::Input;
+                config.edge = Some(Edge::Rising);
+            }
+            SignalKind::Analog => (),
+            SignalKind::AlternateFunction => config.mode = Mode::AlternateFunction,
+        }
+
+        config
+    }
+
+    // Checks the configuration is possible with the selected signal, None means pin
+    // with no signal which keeps the reset state
+    pub fn validate(&self, kind: Option<SignalKind>) -> Result<()> {
+        let invalid = |reason: &str| Err(ErrorKind::InvalidGpioConfig(reason.to_string()).into());
+
+        let modes: &[Mode] = match kind {
+            None => &[Mode::Analog],
+            Some(SignalKind::Gpio) => &[Mode::Input, Mode::Output],
+            Some(SignalKind::Exti) => &[Mode::Input],
+            Some(SignalKind::Analog) => &[Mode::Analog],
+            Some(SignalKind::AlternateFunction) => &[Mode::AlternateFunction],
+        };
+
+        if !modes.contains(&self.mode) {
+            return invalid("mode not permitted by the selected signal");
+        }
+
+        if kind.is_none() && *self != GpioConfig::default() {
+            return invalid("pin with no signal can't be configured");
+        }
+
+        if self.mode == Mode::Analog && self.pull != Pull::None {
+            return invalid("analog pin can't have pull resistor");
+        }
+
+        // Output driver is off in input and analog modes
+        let driven = self.mode == Mode::Output || self.mode == Mode::AlternateFunction;
+
+        if !driven && self.output_type == OutputType::OpenDrain {
+            return invalid("open-drain needs output or alternate function mode");
+        }
+
+        if self.mode != Mode::Output && self.level == Level::High {
+            return invalid("initial level needs output mode");
+        }
+
+        if self.edge.is_some() && self.mode != Mode::Input {
+            return invalid("EXTI trigger needs input mode");
+        }
+
+        if kind == Some(SignalKind::Exti) && self.edge.is_none() {
+            return invalid("EXTI signal needs trigger edge");
+        }
+
+        Ok(())
+    }
+}
+
+// GPIO port pin of package pin name such as "PC14-OSC32_IN" or "PA11 [PA9]"
+pub fn port_name(name: &str) -> &str {
+    match name.find(|c| c == '-' || c == ' ') {
+        Some(idx) => &name[..idx],
+        None => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use pin::{PinBuilder, Position};
+
+    #[test]
+    fn load_modes() {
+        let sample = Path::new("./samples/GPIO-STM32F446_gpio_v1_0_Modes.json");
+        let modes = GpioModes::new(sample).unwrap();
+
+        assert_eq!(modes.name(), "GPIO-STM32F446_gpio_v1_0_Modes");
+        assert_eq!(modes.pins().len(), 116);
+
+        let pin = modes.find_pin("PA10").unwrap();
+        assert_eq!(pin.signals.len(), 4);
+        assert_eq!(
+            pin.signals[2],
+            GpioSignal {
+                name: "USART1_RX".to_string(),
+                value: "AF7".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn load_modes_single_signal() {
+        let sample = Path::new("./samples/GPIO-STM32F446_gpio_v1_0_Modes.json");
+        let modes = GpioModes::new(sample).unwrap();
+
+        let pin = modes.find_pin("PA13").unwrap();
+        assert_eq!(pin.signals.len(), 1);
+        assert_eq!(pin.signals[0].name, "SYS_JTMS-SWDIO");
+    }
+
+    #[test]
+    fn signal_af() {
+        let signal = GpioSignal {
+            name: "USART1_RX".to_string(),
+            value: "AF7".to_string(),
+        };
+        assert_eq!(signal.af(), Some(7));
+
+        let signal = GpioSignal {
+            name: "ADC1_IN0".to_string(),
+            value: "ANALOG".to_string(),
+        };
+        assert_eq!(signal.af(), None);
+    }
+
+    #[test]
+    fn find_pin_port_name() {
+        let sample = Path::new("./samples/GPIO-STM32F446_gpio_v1_0_Modes.json");
+        let modes = GpioModes::new(sample).unwrap();
+
+        let pin = modes.find_pin("PA0").unwrap();
+        assert_eq!(pin.name, "PA0-WKUP");
+    }
+
+    #[test]
+    fn apply_modes() {
+        let sample = Path::new("./samples/GPIO-STM32F446_gpio_v1_0_Modes.json");
+        let modes = GpioModes::new(sample).unwrap();
+
+        let mut pins = Pins {
+            pins: vec![
+                PinBuilder::new("I/O", Position::Linear(42), "PA10")
+                    .reset_signals(vec![
+                        "TIM1_CH3".to_string(),
+                        "USART1_RX".to_string(),
+                        "GPIO".to_string(),
+                    ])
+                    .finish(),
+                PinBuilder::new("Power", Position::Linear(43), "VDD").finish(),
+            ],
+        };
+
+        assert_eq!(modes.apply(&mut pins), 2);
+
+        let params = pins.pins[0].params().unwrap();
+        assert_eq!(params.signal_af("USART1_RX"), Some(7));
+        assert_eq!(params.signal_af("TIM1_CH3"), Some(1));
+        assert_eq!(params.signal_af("GPIO"), None);
+    }
+
+    #[test]
+    fn gpio_config() {
+        let config = GpioConfig::for_signal(SignalKind::AlternateFunction);
+        assert!(config.validate(Some(SignalKind::AlternateFunction)).is_ok());
+        assert!(config.validate(Some(SignalKind::Analog)).is_err());
+        assert!(GpioConfig::default().validate(None).is_ok());
+
+        let config = GpioConfig {
+            mode: Mode::Output,
+            output_type: OutputType::OpenDrain,
+            level: Level::High,
+            ..GpioConfig::default()
+        };
+        assert!(config.validate(Some(SignalKind::Gpio)).is_ok());
+        assert!(config.validate(None).is_err());
+
+        let config = GpioConfig {
+            mode: Mode::Input,
+            level: Level::High,
+            ..GpioConfig::default()
+        };
+        assert!(config.validate(Some(SignalKind::Gpio)).is_err());
+
+        let config = GpioConfig {
+            pull: Pull::Up,
+            ..GpioConfig::default()
+        };
+        assert!(config.validate(Some(SignalKind::Analog)).is_err());
+
+        let mut config = GpioConfig::for_signal(SignalKind::Exti);
+        assert!(config.validate(Some(SignalKind::Exti)).is_ok());
+        config.edge = None;
+        assert!(config.validate(Some(SignalKind::Exti)).is_err());
+        config.edge = Some(Edge::Both);
+        config.mode = Mode::Output;
+        assert!(config.validate(Some(SignalKind::Gpio)).is_err());
+    }
+
+    #[test]
+    fn load_modes_no_signal() {
+        let sample = Path::new("./samples/GPIO-STM32F446_gpio_v1_0_Modes.json");
+        let modes = GpioModes::new(sample).unwrap();
+
+        let pin = modes.find_pin("PC13").unwrap();
+        assert!(pin.signals.is_empty());
+    }
+}
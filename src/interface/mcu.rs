@@ -3,23 +3,39 @@ use std::path::Path;
 use std::os::raw::c_char;
 use std;
 
+use geometry::{Geometry, Side};
 use module::peripheral::Peripheral;
 use package::Package;
 use pins::Pins;
 use mcu::{MCUConf, MCU};
 
+// Values are part of the C ABI, new types are appended
 #[repr(C)]
 pub enum PackageType {
-    LQFP,
-    TSSOP,
-    WLCSP,
-    UFQFPN,
-    TFBGA,
-    VFQFPN,
-    EWLCSP,
-    UFBGA,
-    LFBGA,
-    NONE,
+    LQFP = 0,
+    TSSOP = 1,
+    WLCSP = 2,
+    UFQFPN = 3,
+    TFBGA = 4,
+    VFQFPN = 5,
+    EWLCSP = 6,
+    UFBGA = 7,
+    LFBGA = 8,
+    NONE = 9,
+    SO = 10,
+    SOIC = 11,
+    DIP = 12,
+    QFN = 13,
+    TQFP = 14,
+}
+
+#[repr(C)]
+pub enum PinSide {
+    LEFT = 0,
+    BOTTOM = 1,
+    RIGHT = 2,
+    TOP = 3,
+    NONE = 4,
 }
 
 pub mod cc {
@@ -79,7 +95,7 @@ pub mod cc {
             &mut *ptr
         };
 
-        mcu_conf.get_pins()
+        mcu_conf.get_pins_mut()
     }
 
     #[no_mangle]
@@ -89,7 +105,8 @@ pub mod cc {
             &mut *ptr
         };
 
-        mcu_conf.get_package()
+        // Package is only read through the FFI
+        mcu_conf.get_package() as *const Package as *mut Package
     }
 
     #[no_mangle]
@@ -109,6 +126,12 @@ pub mod cc {
             &mut *ptr
         };
 
+        // Spelling of the name doesn't change the type
+        let package = match *package {
+            Package::Named(ref package, _) => &**package,
+            ref package => package,
+        };
+
         match *package {
             Package::LQFP(..) => PackageType::LQFP,
             Package::TSSOP(..) => PackageType::TSSOP,
@@ -119,10 +142,77 @@ pub mod cc {
             Package::EWLCSP(..) => PackageType::EWLCSP,
             Package::UFBGA(..) => PackageType::UFBGA,
             Package::LFBGA(..) => PackageType::LFBGA,
-            Package::Unknown(..) => PackageType::NONE,
+            Package::SO(..) => PackageType::SO,
+            Package::SOIC(..) => PackageType::SOIC,
+            Package::DIP(..) => PackageType::DIP,
+            Package::QFN(..) => PackageType::QFN,
+            Package::TQFP(..) => PackageType::TQFP,
+            Package::Unknown(..) | Package::Named(..) => PackageType::NONE,
+        }
+    }
+
+    #[no_mangle]
+    pub extern "C" fn package_pins_per_side(ptr: *mut Package) -> u16 {
+        let package = unsafe {
+            assert!(!ptr.is_null());
+            &mut *ptr
+        };
+
+        Geometry::new(package).pins_per_side()
+    }
+
+    #[no_mangle]
+    pub extern "C" fn package_pin_side(ptr: *mut Package, number: u16) -> PinSide {
+        let package = unsafe {
+            assert!(!ptr.is_null());
+            &mut *ptr
+        };
+
+        match Geometry::new(package).side_of(number) {
+            Some((Side::Left, _)) => PinSide::LEFT,
+            Some((Side::Bottom, _)) => PinSide::BOTTOM,
+            Some((Side::Right, _)) => PinSide::RIGHT,
+            Some((Side::Top, _)) => PinSide::TOP,
+            None => PinSide::NONE,
+        }
+    }
+
+    #[no_mangle]
+    pub extern "C" fn package_pin_offset(ptr: *mut Package, number: u16) -> u16 {
+        let package = unsafe {
+            assert!(!ptr.is_null());
+            &mut *ptr
+        };
+
+        match Geometry::new(package).side_of(number) {
+            Some((_, offset)) => offset,
+            None => 0,
         }
     }
 
+    #[no_mangle]
+    pub extern "C" fn package_ball_name(ptr: *mut Package, row: u8, column: u8) -> *mut c_char {
+        let package = unsafe {
+            assert!(!ptr.is_null());
+            &mut *ptr
+        };
+
+        match Geometry::new(package).ball_name(row, column) {
+            Some(name) => CString::new(name).unwrap().into_raw(),
+            None => std::ptr::null_mut(),
+        }
+    }
+
+    #[no_mangle]
+    pub extern "C" fn package_free_ball_name(name: *mut c_char) {
+        unsafe {
+            if name.is_null() {
+                return;
+            }
+            CString::from_raw(name)
+        };
+    }
+
     #[no_mangle]
     pub extern "C" fn mcu_conf_get_peripheral(
         ptr: *mut MCUConf,
@@ -139,7 +229,7 @@ pub mod cc {
         };
 
         match mcu_conf
-            .get_peripherals()
+            .get_peripherals_mut()
             .iter_mut()
             .find(|p| p.name == name.to_str().unwrap())
         {
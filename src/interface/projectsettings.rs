@@ -3,6 +3,7 @@ use std::path::Path;
 use std::os::raw::c_char;
 use std;
 
+use mcu::MCUConf;
 use projectsettings::ProjectSettings;
 
 pub mod cc {
@@ -10,8 +11,33 @@ pub mod cc {
     use super::*;
 
     #[no_mangle]
-    pub extern "C" fn project_settings_new() -> *mut ProjectSettings {
-        Box::into_raw(Box::new(ProjectSettings::new()))
+    pub extern "C" fn project_settings_new(
+        ptr: *mut MCUConf,
+        project_path: *const c_char,
+        resources_path: *const c_char,
+        diff3_path: *const c_char,
+    ) -> *mut ProjectSettings {
+        let mcu_conf = unsafe {
+            assert!(!ptr.is_null());
+            &mut *ptr
+        };
+
+        let path = |path: *const c_char| unsafe {
+            assert!(!path.is_null());
+            CStr::from_ptr(path).to_str().ok().map(Path::new)
+        };
+
+        match (path(project_path), path(resources_path), path(diff3_path)) {
+            (Some(project_path), Some(resources_path), Some(diff3_path)) => {
+                Box::into_raw(Box::new(ProjectSettings::new(
+                    mcu_conf,
+                    project_path,
+                    resources_path,
+                    diff3_path,
+                )))
+            }
+            _ => std::ptr::null_mut(),
+        }
     }
 
     #[no_mangle]
@@ -81,6 +107,12 @@ pub mod cc {
             &mut *ptr
         };
 
+        // Spelling of the name doesn't change the type
+        let package = match *package {
+            Package::Named(ref package, _) => &**package,
+            ref package => package,
+        };
+
         match *package {
             Package::LQFP(..) => PackageType::LQFP,
             Package::TSSOP(..) => PackageType::TSSOP,
@@ -91,7 +123,12 @@ pub mod cc {
             Package::EWLCSP(..) => PackageType::EWLCSP,
             Package::UFBGA(..) => PackageType::UFBGA,
             Package::LFBGA(..) => PackageType::LFBGA,
-            Package::Unknown(..) => PackageType::NONE,
+            Package::SO(..) => PackageType::SO,
+            Package::SOIC(..) => PackageType::SOIC,
+            Package::DIP(..) => PackageType::DIP,
+            Package::QFN(..) => PackageType::QFN,
+            Package::TQFP(..) => PackageType::TQFP,
+            Package::Unknown(..) | Package::Named(..) => PackageType::NONE,
         }
     }
 
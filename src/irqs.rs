@@ -1,13 +1,14 @@
-use std::error::Error;
 use std::fs::File;
 use std::path::Path;
 
 use serde_json;
 
+use errors::*;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct IRQ {
-    pub desc: String,
-    pub value: String,
+    #[serde(rename = "Desc")] pub desc: String,
+    #[serde(rename = "Value")] pub value: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -15,17 +16,251 @@ pub struct IRQS {
     #[serde(rename = "IRQS")] pub irqs: Vec<IRQ>,
 }
 
-struct InterruptBuilder {
+// Flags following the enable flag of NVIC mode entry
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub enum Flag {
+    // Handlers are called only when the HAL parameter in params is set, "IF_HAL"
+    IfHal,
+    // Priority given by the RTOS kernel, "RTOS"
+    Rtos,
+    // Priority constraint of core exceptions such as "W1"
+    PriorityConstraint(u8),
+    // Line shared by several interrupt sources with own handlers such as "2H" or "3H"
+    SharedHandlers(u8),
+    // Line shared by several IPs such as "2V1"
+    SharedIps(u8),
+    // Handler is not tied to any IP, "NO_IP"
+    NoIp,
+    // Handler takes no argument, "NO_ARG"
+    NoArg,
+    Exti,
+    // DMA channel interrupt such as "DMAL0"
+    Dma(u8),
+    Other(String),
+}
+
+impl Flag {
+    fn parse(field: &str) -> Vec<Flag> {
+        let mut flags = vec![];
+
+        for flag in field.split(',').filter(|s| !s.is_empty()) {
+            let (flag, no_ip) = match flag.find("_NO_IP") {
+                Some(idx) => (&flag[..idx], true),
+                None => (flag, false),
+            };
+
+            let parsed = match flag {
+                "IF_HAL" => Flag::IfHal,
+                "RTOS" => Flag::Rtos,
+                "NO_IP" => Flag::NoIp,
+                "NO_ARG" => Flag::NoArg,
+                "EXTI" => Flag::Exti,
+                _ => Flag::numbered(flag),
+            };
+
+            flags.push(parsed);
+            if no_ip {
+                flags.push(Flag::NoIp);
+            }
+        }
+
+        flags
+    }
+
+    fn numbered(flag: &str) -> Flag {
+        let number = |digits: &str| digits.parse::<u8>().ok();
+
+        if let Some(level) = flag.strip_prefix('W').and_then(number) {
+            return Flag::PriorityConstraint(level);
+        }
+        if let Some(level) = flag.strip_prefix("DMAL").and_then(number) {
+            return Flag::Dma(level);
+        }
+        if let Some(count) = flag.strip_suffix('H').and_then(number) {
+            return Flag::SharedHandlers(count);
+        }
+        if let Some(count) = flag.find('V').and_then(|idx| number(&flag[..idx])) {
+            return Flag::SharedIps(count);
+        }
+
+        Flag::Other(flag.to_string())
+    }
+}
+
+// Interrupt class
+//
+//    Decoded NVIC mode entry. The raw value is colon separated list of fields
+//    "IRQn:flags:IPs:handlers:params" where all but the first are comma separated.
+//    The handlers field lists HAL handlers, IP names such as "DMA1" standing for their
+//    HAL handler or is empty when the handlers are those of the IPs.
+//
+//    \sa InterruptBuilder
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Interrupt {
+    pub desc: String,
+    pub irqn: String,
+    pub enableable: bool,
+    pub flags: Vec<Flag>,
+    pub ips: Vec<String>,
+    pub handlers: Vec<String>,
+    pub params: Vec<String>,
+}
+
+impl Interrupt {
+    pub fn new(irq: &IRQ) -> Result<Interrupt> {
+        let fields: Vec<&str> = irq.value.split(':').collect();
+
+        if fields.len() != 5 || fields[0].is_empty() {
+            return Err(ErrorKind::InvalidInterrupt(irq.value.clone()).into());
+        }
+
+        let (enable, flags) = match fields[1].find(',') {
+            Some(idx) => (&fields[1][..idx], &fields[1][idx + 1..]),
+            None => (fields[1], ""),
+        };
+
+        let enableable = match enable {
+            "Y" => true,
+            "N" => false,
+            _ => return Err(ErrorKind::InvalidInterrupt(irq.value.clone()).into()),
+        };
+
+        let flags = Flag::parse(flags);
+        let ips = split_list(fields[2]);
+
+        let named = if fields[3].is_empty() && !flags.contains(&Flag::NoIp) {
+            ips.clone()
+        } else {
+            split_list(fields[3])
+        };
+
+        let mut handlers: Vec<String> = vec![];
+
+        for name in named.iter().filter(|h| *h != "NONE") {
+            let handler = if name.starts_with("HAL_") {
+                name.clone()
+            } else {
+                hal_handler(name)
+            };
+
+            if !handlers.contains(&handler) {
+                handlers.push(handler);
+            }
+        }
+
+        Ok(Interrupt {
+            desc: irq.desc.clone(),
+            irqn: fields[0].to_string(),
+            enableable: enableable,
+            flags: flags,
+            ips: ips,
+            handlers: handlers,
+            params: split_list(fields[4]),
+        })
+    }
+
+    // Name without the "_IRQn" suffix
+    pub fn name(&self) -> &str {
+        if self.irqn.ends_with("_IRQn") {
+            &self.irqn[..self.irqn.len() - 5]
+        } else {
+            &self.irqn
+        }
+    }
+
+    pub fn has_flag(&self, flag: &Flag) -> bool {
+        self.flags.contains(flag)
+    }
+
+    // Interrupt is used by the RTOS kernel and its priority is given by the RTOS
+    pub fn is_rtos(&self) -> bool {
+        self.has_flag(&Flag::Rtos)
+    }
+
+    pub fn priority_constraint(&self) -> Option<u8> {
+        self.flags.iter().find_map(|f| match *f {
+            Flag::PriorityConstraint(level) => Some(level),
+            _ => None,
+        })
+    }
+
+    // Interrupt line shared by several IPs or interrupt sources
+    pub fn is_shared(&self) -> bool {
+        self.ips.len() > 1
+            || self
+                .flags
+                .iter()
+                .any(|f| matches!(*f, Flag::SharedHandlers(_) | Flag::SharedIps(_)))
+    }
+
+    pub fn belongs_to(&self, ip: &str) -> bool {
+        self.ips.iter().any(|i| i == ip)
+    }
+}
+
+// HAL handler of IP such as "HAL_UART_IRQHandler" of "USART1"
+fn hal_handler(ip: &str) -> String {
+    let base = ip.trim_end_matches(|c: char| c.is_ascii_digit());
+
+    let base = match base {
+        "USART" | "UART" | "LPUART" => "UART",
+        "HDMI_CEC" => "CEC",
+        _ => base,
+    };
+
+    format!("HAL_{}_IRQHandler", base)
+}
+
+fn split_list(field: &str) -> Vec<String> {
+    field
+        .split(',')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect()
+}
+
+pub struct InterruptBuilder {
     irqs: IRQS,
 }
 
 impl InterruptBuilder {
-    pub fn new(path: &Path) -> Result<InterruptBuilder, Box<Error>> {
+    pub fn new(path: &Path) -> Result<InterruptBuilder> {
         let file = File::open(path)?;
         let irqs: IRQS = serde_json::from_reader(file)?;
 
         Ok(InterruptBuilder { irqs: irqs })
     }
+
+    pub fn irqs(&self) -> &IRQS {
+        &self.irqs
+    }
+
+    // Decodes all interrupts keeping the device order
+    pub fn finish(self) -> Result<Vec<Interrupt>> {
+        self.irqs.irqs.iter().map(Interrupt::new).collect()
+    }
+}
+
+// Lists names of HAL handlers the IP needs
+pub fn ip_handlers<'a>(interrupts: &'a [Interrupt], ip: &str) -> Vec<&'a str> {
+    let mut handlers: Vec<&str> = vec![];
+
+    for interrupt in interrupts.iter().filter(|i| i.belongs_to(ip)) {
+        let shared = interrupt.ips.len() > 1 && interrupt.handlers.len() == interrupt.ips.len();
+
+        for (idx, handler) in interrupt.handlers.iter().enumerate() {
+            // Handlers of line shared by IPs follow the order of the IPs
+            if shared && interrupt.ips[idx] != ip {
+                continue;
+            }
+
+            if !handlers.contains(&handler.as_str()) {
+                handlers.push(handler);
+            }
+        }
+    }
+
+    handlers
 }
 
 #[cfg(test)]
@@ -35,10 +270,128 @@ mod tests {
 
     #[test]
     fn load_irqs() {
+        let sample = Path::new("./samples/NVIC-STM32F042_Modes.json");
+        let irqs = InterruptBuilder::new(sample);
+
+        assert!(irqs.is_ok());
+        assert_eq!(irqs.unwrap().irqs().irqs.len(), 35);
+    }
 
-        //let sample = Path::new("./samples/NVIC-STM32F042_Modes.json");
-        //let irqs = InterruptBuilder::new(sample);
+    #[test]
+    fn decode_irqs() {
+        let sample = Path::new("./samples/NVIC-STM32F042_Modes.json");
+        let interrupts = InterruptBuilder::new(sample).unwrap().finish().unwrap();
+
+        assert_eq!(interrupts.len(), 35);
+        assert_eq!(interrupts[0].irqn, "NonMaskableInt_IRQn");
+        assert_eq!(interrupts[0].enableable, false);
+        assert_eq!(interrupts[0].flags, vec![Flag::IfHal]);
+        assert_eq!(
+            interrupts[0].handlers,
+            vec!["HAL_RCC_NMI_IRQHandler".to_string()]
+        );
+        assert_eq!(interrupts[0].params, vec!["CSSEnabled".to_string()]);
+        assert_eq!(interrupts[1].priority_constraint(), Some(1));
+        assert_eq!(
+            interrupts[6].flags,
+            vec![Flag::SharedHandlers(2), Flag::NoIp]
+        );
+    }
+
+    #[test]
+    fn decode_ip_handlers() {
+        let sample = Path::new("./samples/NVIC-STM32F042_Modes.json");
+        let interrupts = InterruptBuilder::new(sample).unwrap().finish().unwrap();
+
+        let find = |irqn: &str| interrupts.iter().find(|i| i.irqn == irqn).unwrap();
+
+        let dma = find("DMA1_Channel1_IRQn");
+        assert_eq!(dma.flags, vec![Flag::Dma(0)]);
+        assert_eq!(dma.handlers, vec!["HAL_DMA_IRQHandler".to_string()]);
+
+        let cec_can = find("CEC_CAN_IRQn");
+        assert_eq!(cec_can.flags, vec![Flag::SharedIps(2)]);
+        assert_eq!(
+            cec_can.handlers,
+            vec![
+                "HAL_CEC_IRQHandler".to_string(),
+                "HAL_CAN_IRQHandler".to_string(),
+            ]
+        );
+
+        assert_eq!(
+            find("SPI1_IRQn").handlers,
+            vec![
+                "HAL_SPI_IRQHandler".to_string(),
+                "HAL_I2S_IRQHandler".to_string(),
+            ]
+        );
+        assert!(find("RCC_IRQn").handlers.is_empty());
+        assert!(find("SysTick_IRQn").handlers.is_empty());
+    }
+
+    #[test]
+    fn decode_shared() {
+        let irq = IRQ {
+            desc: "RTC interrupt through EXTI lines 17, 19 and 20".to_string(),
+            value: "RTC_IRQn:Y,3H:RTC:HAL_RTC_AlarmIRQHandler,HAL_RTCEx_WakeUpTimerIRQHandler,HAL_RTCEx_TamperTimeStampIRQHandler:Alarm,WakeUp,(Timestamp|Tamper)".to_string(),
+        };
+
+        let interrupt = Interrupt::new(&irq).unwrap();
+
+        assert_eq!(interrupt.name(), "RTC");
+        assert_eq!(interrupt.enableable, true);
+        assert_eq!(interrupt.ips, vec!["RTC".to_string()]);
+        assert_eq!(interrupt.handlers.len(), 3);
+        assert!(interrupt.is_shared());
+        assert!(!interrupt.is_rtos());
+    }
+
+    #[test]
+    fn decode_rtos() {
+        let irq = IRQ {
+            desc: "System service call via SWI instruction".to_string(),
+            value: "SVC_IRQn:Y,RTOS::NONE:".to_string(),
+        };
+
+        let interrupt = Interrupt::new(&irq).unwrap();
+
+        assert!(interrupt.is_rtos());
+        assert!(interrupt.ips.is_empty());
+        assert!(interrupt.handlers.is_empty());
+    }
+
+    #[test]
+    fn decode_invalid() {
+        let irq = IRQ {
+            desc: "Broken".to_string(),
+            value: "USART1_IRQn:X:USART1::".to_string(),
+        };
+        assert!(Interrupt::new(&irq).is_err());
+
+        let irq = IRQ {
+            desc: "Broken".to_string(),
+            value: "USART1_IRQn:Y".to_string(),
+        };
+        assert!(Interrupt::new(&irq).is_err());
+    }
+
+    #[test]
+    fn handlers_for_ip() {
+        let sample = Path::new("./samples/NVIC-STM32F042_Modes.json");
+        let interrupts = InterruptBuilder::new(sample).unwrap().finish().unwrap();
 
-        //assert!(irqs.is_ok());
+        assert_eq!(
+            ip_handlers(&interrupts, "I2C1"),
+            vec!["HAL_I2C_ER_IRQHandler", "HAL_I2C_EV_IRQHandler"]
+        );
+        assert_eq!(ip_handlers(&interrupts, "TIM1"), vec!["HAL_TIM_IRQHandler"]);
+        assert_eq!(
+            ip_handlers(&interrupts, "USART1"),
+            vec!["HAL_UART_IRQHandler"]
+        );
+        assert_eq!(ip_handlers(&interrupts, "DMA"), vec!["HAL_DMA_IRQHandler"]);
+        assert_eq!(ip_handlers(&interrupts, "CAN"), vec!["HAL_CAN_IRQHandler"]);
+        assert_eq!(ip_handlers(&interrupts, "I2S1"), vec!["HAL_I2S_IRQHandler"]);
     }
 }
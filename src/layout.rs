@@ -0,0 +1,283 @@
+use errors::*;
+use mcu::MemoryConfiguration;
+use memory::Memory;
+
+const ALIGN: u64 = 8;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Bank {
+    start: u64,
+    end: u64,
+    // Free space spans from low to high
+    low: u64,
+    high: u64,
+    // Heap buffers may be handed to DMA, CCM can't hold them
+    dma: bool,
+}
+
+impl Bank {
+    pub fn start(&self) -> u32 {
+        self.start as u32
+    }
+
+    pub fn size(&self) -> u32 {
+        (self.end - self.start) as u32
+    }
+
+    pub fn free(&self) -> u32 {
+        (self.high - self.low) as u32
+    }
+}
+
+// LayoutPlanner class
+//
+//    Places stack and heap into the RAM banks. Static data are reserved at the start of
+//    the main RAM, stack is placed at the top of a bank and grows down, heap takes the
+//    lowest free space. Both prefer the main RAM and fall back to other banks, the heap
+//    only to banks reachable by DMA.
+//
+//    \sa MemoryConfiguration
+#[derive(Debug)]
+pub struct LayoutPlanner {
+    banks: Vec<Bank>,
+}
+
+impl LayoutPlanner {
+    pub fn new(memory: &[Memory]) -> LayoutPlanner {
+        let banks = memory
+            .iter()
+            .filter(|m| m.is_ram())
+            .map(|m| {
+                let start = u64::from(m.start());
+                let end = start + u64::from(m.size());
+
+                Bank {
+                    start: start,
+                    end: end,
+                    low: align_up(start),
+                    high: end & !(ALIGN - 1),
+                    dma: m.attributes().dma,
+                }
+            })
+            .collect();
+
+        LayoutPlanner { banks: banks }
+    }
+
+    pub fn banks(&self) -> &Vec<Bank> {
+        &self.banks
+    }
+
+    // Total free RAM left after placement
+    pub fn free(&self) -> u32 {
+        self.banks.iter().map(|b| b.free()).sum()
+    }
+
+    // Reserves space for .data and .bss at the start of the main RAM
+    pub fn reserve(&mut self, size: u32) -> Result<()> {
+        let size = align_up(u64::from(size));
+
+        match self.banks.first_mut() {
+            Some(ref mut bank) if bank.high - bank.low >= size => {
+                bank.low += size;
+                Ok(())
+            }
+            _ => Err(ErrorKind::MemoryOverflow(String::from("static data"), size as u32).into()),
+        }
+    }
+
+    // Places stack and heap, the planner is left untouched when either doesn't fit
+    pub fn plan(&mut self, stack_size: u32, heap_size: u32) -> Result<MemoryConfiguration> {
+        let mut banks = self.banks.clone();
+        let mut stack_addr = 0;
+        let mut heap_addr = 0;
+
+        if stack_size > 0 {
+            let size = align_up(u64::from(stack_size));
+
+            match banks.iter_mut().find(|b| b.high - b.low >= size) {
+                Some(bank) => {
+                    bank.high -= size;
+                    stack_addr = bank.high as u32;
+                }
+                None => {
+                    return Err(ErrorKind::MemoryOverflow(String::from("stack"), stack_size).into())
+                }
+            }
+        }
+
+        if heap_size > 0 {
+            let size = align_up(u64::from(heap_size));
+
+            match banks.iter_mut().find(|b| b.dma && b.high - b.low >= size) {
+                Some(bank) => {
+                    heap_addr = bank.low as u32;
+                    bank.low += size;
+                }
+                None => {
+                    return Err(ErrorKind::MemoryOverflow(String::from("heap"), heap_size).into())
+                }
+            }
+        }
+
+        self.banks = banks;
+
+        Ok(MemoryConfiguration::new(
+            stack_addr, stack_size, heap_addr, heap_size,
+        ))
+    }
+}
+
+// Checks stack and heap of the configuration lie within RAM and don't overlap
+pub fn check_configuration(memory: &[Memory], configuration: &MemoryConfiguration) -> Result<()> {
+    let stack = (
+        u64::from(configuration.stack_addr()),
+        u64::from(configuration.stack_addr()) + u64::from(configuration.stack_size()),
+    );
+    let heap = (
+        u64::from(configuration.heap_addr()),
+        u64::from(configuration.heap_addr()) + u64::from(configuration.heap_size()),
+    );
+
+    let in_ram = |range: (u64, u64)| {
+        memory.iter().any(|m| {
+            m.is_ram()
+                && range.0 >= u64::from(m.start())
+                && range.1 <= u64::from(m.start()) + u64::from(m.size())
+        })
+    };
+
+    if configuration.stack_size() > 0 && !in_ram(stack) {
+        return Err(
+            ErrorKind::MemoryOverflow(String::from("stack"), configuration.stack_size()).into(),
+        );
+    }
+
+    if configuration.heap_size() > 0 && !in_ram(heap) {
+        return Err(
+            ErrorKind::MemoryOverflow(String::from("heap"), configuration.heap_size()).into(),
+        );
+    }
+
+    if configuration.stack_size() > 0
+        && configuration.heap_size() > 0
+        && stack.0 < heap.1
+        && heap.0 < stack.1
+    {
+        return Err(ErrorKind::MemoryOverlap(String::from("stack"), String::from("heap")).into());
+    }
+
+    Ok(())
+}
+
+fn align_up(value: u64) -> u64 {
+    (value + ALIGN - 1) & !(ALIGN - 1)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn stm32f030() -> Vec<Memory> {
+        vec![
+            Memory::Flash {
+                start: 0x08000000,
+                size: 32768,
+                sectors: vec![],
+            },
+            Memory::Ram {
+                start: 0x20000000,
+                size: 4096,
+            },
+        ]
+    }
+
+    #[test]
+    fn plan_single_bank() {
+        let memory = stm32f030();
+        let mut planner = LayoutPlanner::new(&memory);
+
+        planner.reserve(1000).unwrap();
+        let configuration = planner.plan(0x400, 0x200).unwrap();
+
+        assert_eq!(configuration.stack_addr(), 0x20000C00);
+        assert_eq!(configuration.stack_size(), 0x400);
+        assert_eq!(configuration.heap_addr(), 0x200003E8);
+        assert_eq!(configuration.heap_size(), 0x200);
+        assert_eq!(planner.free(), 4096 - 1000 - 0x400 - 0x200);
+        assert!(check_configuration(&memory, &configuration).is_ok());
+    }
+
+    #[test]
+    fn plan_overflow() {
+        let memory = stm32f030();
+        let mut planner = LayoutPlanner::new(&memory);
+
+        planner.reserve(1024).unwrap();
+
+        assert!(planner.plan(0x400, 0x900).is_err());
+        assert_eq!(planner.free(), 3072);
+        assert!(planner.plan(0x400, 0x800).is_ok());
+        assert_eq!(planner.free(), 0);
+        assert!(LayoutPlanner::new(&memory).plan(0x400, 0x1000).is_err());
+        assert!(LayoutPlanner::new(&memory).reserve(0x2000).is_err());
+    }
+
+    #[test]
+    fn plan_multiple_banks() {
+        let mut memory = stm32f030();
+        memory.push(Memory::Ram {
+            start: 0x10000000,
+            size: 0x10000,
+        });
+        let mut planner = LayoutPlanner::new(&memory);
+
+        planner.reserve(3072).unwrap();
+        let configuration = planner.plan(0x400, 0x2000).unwrap();
+
+        assert_eq!(configuration.stack_addr(), 0x20000C00);
+        assert_eq!(configuration.heap_addr(), 0x10000000);
+        assert_eq!(planner.banks()[0].free(), 0);
+        assert_eq!(planner.banks()[1].free(), 0xE000);
+    }
+
+    #[test]
+    fn plan_ccm() {
+        let memory = vec![
+            Memory::Ram {
+                start: 0x20000000,
+                size: 0x20000,
+            },
+            Memory::Ccm {
+                start: 0x10000000,
+                size: 0x10000,
+            },
+        ];
+        let mut planner = LayoutPlanner::new(&memory);
+
+        planner.reserve(0x1C000).unwrap();
+
+        // Stack may go to CCM, heap must stay in DMA reachable RAM
+        let configuration = planner.plan(0x8000, 0x2000).unwrap();
+        assert_eq!(configuration.stack_addr(), 0x10008000);
+        assert_eq!(configuration.heap_addr(), 0x2001C000);
+
+        assert!(planner.plan(0, 0x4000).is_err());
+        assert!(planner.plan(0x4000, 0).is_ok());
+    }
+
+    #[test]
+    fn check_overlap() {
+        let memory = stm32f030();
+
+        let overlap = MemoryConfiguration::new(0x20000800, 0x400, 0x20000A00, 0x400);
+        assert!(check_configuration(&memory, &overlap).is_err());
+
+        let outside = MemoryConfiguration::new(0x20000E00, 0x400, 0, 0);
+        assert!(check_configuration(&memory, &outside).is_err());
+
+        let empty = MemoryConfiguration::new(0, 0, 0, 0);
+        assert!(check_configuration(&memory, &empty).is_ok());
+    }
+}
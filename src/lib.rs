@@ -1,3 +1,5 @@
+#![recursion_limit = "256"]
+
 #[macro_use]
 extern crate error_chain;
 #[macro_use]
@@ -14,16 +16,31 @@ extern crate serde_json;
 pub mod interface;
 pub mod mcu;
 pub mod memory;
+pub mod layout;
+pub mod partition;
+pub mod clock;
+pub mod frequency;
 pub mod package;
+pub mod geometry;
 pub mod module;
 pub mod projectsettings;
 pub mod cargo;
+pub mod linker;
+pub mod pinout;
+pub mod diagram;
+pub mod database;
+pub mod search;
+pub mod gpio;
 mod errors;
 
 pub mod irqs;
+pub mod nvic;
+pub mod vectors;
 
 pub mod pins;
+pub mod conflicts;
 pub mod pin;
+pub mod signal;
 
 use errors::*;
 
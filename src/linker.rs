@@ -0,0 +1,444 @@
+use std::fmt::Write;
+
+use errors::*;
+use mcu::{MCUConf, MemoryConfiguration};
+use memory::Memory;
+use partition::Partition;
+
+#[derive(Debug, PartialEq)]
+pub struct Region {
+    pub name: String,
+    pub start: u32,
+    pub size: u32,
+    // Region may hold stack and heap
+    pub ram: bool,
+}
+
+// LinkerScript class
+//
+//    Generates linker scripts from the memory map of the MCU. Regions are named after the
+//    memory (FLASH, RAM, CCMRAM, ...), further regions of the same kind get numbered
+//    suffix. OTP, option bytes and system memory are left out.
+//
+//    \sa Memory, MemoryConfiguration
+#[derive(Debug)]
+pub struct LinkerScript<'a> {
+    name: &'a str,
+    regions: Vec<Region>,
+    configuration: &'a MemoryConfiguration,
+}
+
+impl<'a> LinkerScript<'a> {
+    pub fn new(mcu_conf: &'a MCUConf) -> LinkerScript<'a> {
+        let mut regions: Vec<Region> = vec![];
+
+        for memory in mcu_conf.get_memory() {
+            // Nothing gets linked into these
+            match *memory {
+                Memory::Otp { .. } | Memory::OptionBytes { .. } | Memory::SystemRom { .. } => {
+                    continue
+                }
+                _ => (),
+            }
+
+            let kind = memory.name();
+
+            let count = regions.iter().filter(|r| r.name.starts_with(kind)).count();
+
+            let name = match count {
+                0 => kind.to_string(),
+                n => format!("{}{}", kind, n + 1),
+            };
+
+            regions.push(Region {
+                name: name,
+                start: memory.start(),
+                size: memory.size(),
+                ram: memory.is_ram(),
+            });
+        }
+
+        LinkerScript {
+            name: mcu_conf.get_name(),
+            regions: regions,
+            configuration: mcu_conf.get_memory_configuration(),
+        }
+    }
+
+    pub fn regions(&self) -> &Vec<Region> {
+        &self.regions
+    }
+
+    // Splits main Flash into partitions, the target one becomes FLASH holding the code
+    // while the others are listed for reference, e.g. APP in the bootloader script
+    pub fn partition_flash(&mut self, partitions: &[Partition], target: &str) -> Result<()> {
+        if !partitions.iter().any(|p| p.name == target) {
+            return Err(ErrorKind::UnknownPartition(target.to_string()).into());
+        }
+
+        let idx = match self.regions.iter().position(|r| r.name == "FLASH") {
+            Some(idx) => idx,
+            None => return Err(ErrorKind::UnknownPartition(String::from("FLASH")).into()),
+        };
+
+        let flash: Vec<Region> = partitions
+            .iter()
+            .map(|p| Region {
+                name: if p.name == target {
+                    String::from("FLASH")
+                } else {
+                    p.name.clone()
+                },
+                start: p.start,
+                size: p.size,
+                ram: false,
+            })
+            .collect();
+
+        self.regions.splice(idx..idx + 1, flash);
+
+        Ok(())
+    }
+
+    // Region holding the address, main RAM if there's none
+    fn ram_region(&self, addr: u32) -> &str {
+        let found = self.regions.iter().find(|r| {
+            r.ram
+                && addr >= r.start
+                && u64::from(addr) < u64::from(r.start) + u64::from(r.size)
+        });
+
+        match found {
+            Some(region) => &region.name,
+            None => "RAM",
+        }
+    }
+
+    fn write_memory(&self, out: &mut String) {
+        writeln!(out, "MEMORY").unwrap();
+        writeln!(out, "{{").unwrap();
+
+        for region in &self.regions {
+            writeln!(
+                out,
+                "  {} : ORIGIN = 0x{:08X}, LENGTH = {}",
+                region.name,
+                region.start,
+                length(region.size)
+            )
+            .unwrap();
+        }
+
+        writeln!(out, "}}").unwrap();
+    }
+
+    // Stack top, end of main RAM unless the stack is placed explicitly
+    fn stack_start(&self) -> String {
+        if self.configuration.stack_size() > 0 {
+            format!(
+                "0x{:08X}",
+                u64::from(self.configuration.stack_addr())
+                    + u64::from(self.configuration.stack_size())
+            )
+        } else {
+            String::from("ORIGIN(RAM) + LENGTH(RAM)")
+        }
+    }
+
+    // Memory layout for cortex-m-rt
+    pub fn memory_x(&self) -> String {
+        let mut out = String::new();
+
+        writeln!(out, "/* Memory layout of {} */", self.name).unwrap();
+        self.write_memory(&mut out);
+        writeln!(out).unwrap();
+        writeln!(out, "_stack_start = {};", self.stack_start()).unwrap();
+
+        let heap_size = self.configuration.heap_size();
+
+        if heap_size > 0 {
+            let heap_addr = self.configuration.heap_addr();
+
+            writeln!(out).unwrap();
+            writeln!(out, "SECTIONS").unwrap();
+            writeln!(out, "{{").unwrap();
+
+            if heap_addr > 0 {
+                writeln!(out, "  .heap 0x{:08X} (NOLOAD) : ALIGN(4)", heap_addr).unwrap();
+            } else {
+                writeln!(out, "  .heap (NOLOAD) : ALIGN(4)").unwrap();
+            }
+
+            writeln!(out, "  {{").unwrap();
+            writeln!(out, "    _sheap = .;").unwrap();
+            writeln!(out, "    . += 0x{:X};", heap_size).unwrap();
+            writeln!(out, "    _eheap = .;").unwrap();
+            writeln!(out, "  }} > {}", self.ram_region(heap_addr)).unwrap();
+            writeln!(out, "}} INSERT AFTER .bss;").unwrap();
+        }
+
+        out
+    }
+
+    // Standalone GNU ld script for C projects
+    pub fn gnu_ld(&self) -> String {
+        let mut out = String::new();
+
+        writeln!(out, "/* Linker script of {} */", self.name).unwrap();
+        writeln!(out).unwrap();
+        writeln!(out, "ENTRY(Reset_Handler)").unwrap();
+        writeln!(out).unwrap();
+        writeln!(out, "_estack = {};", self.stack_start()).unwrap();
+        writeln!(
+            out,
+            "_Min_Heap_Size = 0x{:X};",
+            self.configuration.heap_size()
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "_Min_Stack_Size = 0x{:X};",
+            self.configuration.stack_size()
+        )
+        .unwrap();
+        writeln!(out).unwrap();
+        self.write_memory(&mut out);
+        writeln!(out).unwrap();
+        out.push_str(GNU_LD_SECTIONS);
+
+        out
+    }
+}
+
+const GNU_LD_SECTIONS: &str = "SECTIONS
+{
+  .isr_vector :
+  {
+    . = ALIGN(4);
+    KEEP(*(.isr_vector))
+    . = ALIGN(4);
+  } > FLASH
+
+  .text :
+  {
+    . = ALIGN(4);
+    *(.text)
+    *(.text*)
+    KEEP (*(.init))
+    KEEP (*(.fini))
+    . = ALIGN(4);
+    _etext = .;
+  } > FLASH
+
+  .rodata :
+  {
+    . = ALIGN(4);
+    *(.rodata)
+    *(.rodata*)
+    . = ALIGN(4);
+  } > FLASH
+
+  _sidata = LOADADDR(.data);
+
+  .data :
+  {
+    . = ALIGN(4);
+    _sdata = .;
+    *(.data)
+    *(.data*)
+    . = ALIGN(4);
+    _edata = .;
+  } > RAM AT> FLASH
+
+  .bss (NOLOAD) :
+  {
+    . = ALIGN(4);
+    _sbss = .;
+    *(.bss)
+    *(.bss*)
+    *(COMMON)
+    . = ALIGN(4);
+    _ebss = .;
+  } > RAM
+
+  ._user_heap_stack (NOLOAD) :
+  {
+    . = ALIGN(8);
+    PROVIDE ( end = . );
+    PROVIDE ( _end = . );
+    . = . + _Min_Heap_Size;
+    . = . + _Min_Stack_Size;
+    . = ALIGN(8);
+  } > RAM
+}
+";
+
+fn length(size: u32) -> String {
+    if size > 0 && size % (1024 * 1024) == 0 {
+        format!("{}M", size / (1024 * 1024))
+    } else if size > 0 && size % 1024 == 0 {
+        format!("{}K", size / 1024)
+    } else {
+        format!("0x{:X}", size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use mcu::MCU;
+    use std::path::Path;
+
+    #[test]
+    fn regions() {
+        let sample = Path::new("./samples/STM32F030C6Tx.json");
+        let mut mcu_conf = MCU::new(sample).unwrap().finish();
+        mcu_conf.get_memory_mut().push(Memory::Ram {
+            start: 0x10000000,
+            size: 0x10000,
+        });
+        mcu_conf.get_memory_mut().push(Memory::Ccm {
+            start: 0x10010000,
+            size: 0x2000,
+        });
+        mcu_conf.get_memory_mut().push(Memory::OptionBytes {
+            start: 0x1FFFF800,
+            size: 16,
+        });
+
+        let script = LinkerScript::new(&mcu_conf);
+
+        assert_eq!(
+            *script.regions(),
+            vec![
+                Region {
+                    name: "FLASH".to_string(),
+                    start: 0x08000000,
+                    size: 32768,
+                    ram: false,
+                },
+                Region {
+                    name: "RAM".to_string(),
+                    start: 0x20000000,
+                    size: 4096,
+                    ram: true,
+                },
+                Region {
+                    name: "RAM2".to_string(),
+                    start: 0x10000000,
+                    size: 0x10000,
+                    ram: true,
+                },
+                Region {
+                    name: "CCMRAM".to_string(),
+                    start: 0x10010000,
+                    size: 0x2000,
+                    ram: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn memory_x() {
+        let sample = Path::new("./samples/STM32F030C6Tx.json");
+        let mcu_conf = MCU::new(sample).unwrap().finish();
+
+        let script = LinkerScript::new(&mcu_conf);
+
+        assert_eq!(
+            script.memory_x(),
+            "/* Memory layout of STM32F030C6Tx */
+MEMORY
+{
+  FLASH : ORIGIN = 0x08000000, LENGTH = 32K
+  RAM : ORIGIN = 0x20000000, LENGTH = 4K
+}
+
+_stack_start = ORIGIN(RAM) + LENGTH(RAM);
+"
+        );
+    }
+
+    #[test]
+    fn memory_x_heap() {
+        let sample = Path::new("./samples/STM32F030C6Tx.json");
+        let mut mcu_conf = MCU::new(sample).unwrap().finish();
+        *mcu_conf.get_memory_configuration_mut() =
+            MemoryConfiguration::new(0x20000C00, 0x400, 0x20000800, 0x400);
+
+        let script = LinkerScript::new(&mcu_conf);
+        let memory_x = script.memory_x();
+
+        assert!(memory_x.contains("_stack_start = 0x20001000;"));
+        assert!(memory_x.contains("  .heap 0x20000800 (NOLOAD) : ALIGN(4)\n"));
+        assert!(memory_x.contains("    . += 0x400;\n"));
+        assert!(memory_x.contains("  } > RAM\n} INSERT AFTER .bss;"));
+    }
+
+    #[test]
+    fn memory_x_heap_sram2() {
+        let sample = Path::new("./samples/STM32F030C6Tx.json");
+        let mut mcu_conf = MCU::new(sample).unwrap().finish();
+        mcu_conf.get_memory_mut().push(Memory::Sram {
+            name: "SRAM2".to_string(),
+            start: 0x10000000,
+            size: 0x8000,
+        });
+        *mcu_conf.get_memory_configuration_mut() =
+            MemoryConfiguration::new(0x20000C00, 0x400, 0x10000000, 0x4000);
+
+        let script = LinkerScript::new(&mcu_conf);
+
+        assert!(script.memory_x().contains("  } > SRAM2\n} INSERT AFTER .bss;"));
+    }
+
+    #[test]
+    fn gnu_ld() {
+        let sample = Path::new("./samples/STM32F030C6Tx.json");
+        let mcu_conf = MCU::new(sample).unwrap().finish();
+
+        let script = LinkerScript::new(&mcu_conf);
+        let ld = script.gnu_ld();
+
+        assert!(ld.contains("ENTRY(Reset_Handler)"));
+        assert!(ld.contains("_estack = ORIGIN(RAM) + LENGTH(RAM);"));
+        assert!(ld.contains("  FLASH : ORIGIN = 0x08000000, LENGTH = 32K\n"));
+        assert!(ld.contains("} > RAM AT> FLASH"));
+    }
+
+    #[test]
+    fn partitioned() {
+        let sample = Path::new("./samples/STM32F030C6Tx.json");
+        let mcu_conf = MCU::new(sample).unwrap().finish();
+        let partitions = vec![
+            Partition {
+                name: "BOOTLOADER".to_string(),
+                start: 0x08000000,
+                size: 0x1000,
+            },
+            Partition {
+                name: "APP".to_string(),
+                start: 0x08001000,
+                size: 0x7000,
+            },
+        ];
+
+        let mut script = LinkerScript::new(&mcu_conf);
+        assert!(script.partition_flash(&partitions, "MISSING").is_err());
+        script.partition_flash(&partitions, "APP").unwrap();
+        let memory_x = script.memory_x();
+
+        assert!(memory_x.contains("  BOOTLOADER : ORIGIN = 0x08000000, LENGTH = 4K\n"));
+        assert!(memory_x.contains("  FLASH : ORIGIN = 0x08001000, LENGTH = 28K\n"));
+        assert!(memory_x.contains("  RAM : ORIGIN = 0x20000000, LENGTH = 4K\n"));
+    }
+
+    #[test]
+    fn region_length() {
+        assert_eq!(length(0x100000), "1M");
+        assert_eq!(length(6144), "6K");
+        assert_eq!(length(100), "0x64");
+    }
+}
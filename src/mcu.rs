@@ -5,10 +5,16 @@ use module::peripheral::Peripheral;
 use pin::Pin;
 use pins::Pins;
 use package::Package;
+use nvic::NvicConfig;
+use layout::LayoutPlanner;
+use clock::{ClockConfig, ClockRequest, ClockTree};
 
+use serde::de::{self, Deserialize, Deserializer};
 use serde_json;
 use errors::*;
-use memory::Memory;
+use memory::{self, Memory};
+use database::McuInfo;
+pub use frequency::Frequency;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Platform {
@@ -18,15 +24,16 @@ pub enum Platform {
     MSP430,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub enum ARMCore {
     CortexM0,
+    CortexM0Plus,
     CortexM3,
     CortexM4,
     CortexM7,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub enum Core {
     ARM(ARMCore),
     AVR,
@@ -42,9 +49,31 @@ pub struct MemoryConfiguration {
     heap_size: u32,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
-pub enum Frequency {
-    MHz(u16),
+impl MemoryConfiguration {
+    pub fn new(stack_addr: u32, stack_size: u32, heap_addr: u32, heap_size: u32) -> MemoryConfiguration {
+        MemoryConfiguration {
+            stack_addr: stack_addr,
+            stack_size: stack_size,
+            heap_addr: heap_addr,
+            heap_size: heap_size,
+        }
+    }
+
+    pub fn stack_addr(&self) -> u32 {
+        self.stack_addr
+    }
+
+    pub fn stack_size(&self) -> u32 {
+        self.stack_size
+    }
+
+    pub fn heap_addr(&self) -> u32 {
+        self.heap_addr
+    }
+
+    pub fn heap_size(&self) -> u32 {
+        self.heap_size
+    }
 }
 
 #[allow(non_snake_case)]
@@ -109,6 +138,8 @@ impl MCU {
             heap_size: 0,
         };
 
+        let nvic = NvicConfig::new(&self.core);
+
         MCUConf {
             memory: self.memory,
             memory_configuration: memory_configuration,
@@ -121,11 +152,13 @@ impl MCU {
             middlewares: middlewares,
             components: components,
             pins: Pins { pins: self.pins },
+            nvic: nvic,
+            clock_config: None,
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Debug)]
 pub struct MCUConf {
     memory: Vec<Memory>,
     memory_configuration: MemoryConfiguration,
@@ -138,6 +171,70 @@ pub struct MCUConf {
     middlewares: Vec<String>,
     components: Vec<String>,
     pins: Pins,
+    nvic: NvicConfig,
+    #[serde(default)]
+    clock_config: Option<ClockConfig>,
+}
+
+// Configurations saved before NVIC support carry no nvic, it is derived from the core
+#[derive(Deserialize)]
+struct RawMCUConf {
+    memory: Vec<Memory>,
+    memory_configuration: MemoryConfiguration,
+    frequency: Frequency,
+    platform: Platform,
+    core: Core,
+    name: String,
+    package: Package,
+    periherals: Vec<Peripheral>,
+    middlewares: Vec<String>,
+    components: Vec<String>,
+    pins: Pins,
+    #[serde(default)]
+    nvic: Option<NvicConfig>,
+    #[serde(default)]
+    clock_config: Option<ClockConfig>,
+}
+
+impl<'de> Deserialize<'de> for MCUConf {
+    fn deserialize<D>(deserializer: D) -> ::std::result::Result<MCUConf, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let raw = RawMCUConf::deserialize(deserializer)?;
+
+        // Priority bits are fixed by the core, a stored block must agree with it
+        let core_nvic = NvicConfig::new(&raw.core);
+
+        let nvic = match raw.nvic {
+            Some(nvic) => {
+                if nvic.priority_bits() != core_nvic.priority_bits() {
+                    let error = Error::from(ErrorKind::InvalidPriorityBits(nvic.priority_bits()));
+                    return Err(de::Error::custom(error));
+                }
+
+                nvic.validate().map_err(de::Error::custom)?;
+                nvic
+            }
+            None => core_nvic,
+        };
+
+        Ok(MCUConf {
+            memory: raw.memory,
+            memory_configuration: raw.memory_configuration,
+            frequency: raw.frequency,
+            platform: raw.platform,
+            core: raw.core,
+            name: raw.name,
+            package: raw.package,
+            periherals: raw.periherals,
+            middlewares: raw.middlewares,
+            components: raw.components,
+            pins: raw.pins,
+            nvic: nvic,
+            clock_config: raw.clock_config,
+        })
+    }
 }
 
 impl MCUConf {
@@ -192,12 +289,128 @@ impl MCUConf {
     pub fn get_memory_configuration_mut(&mut self) -> &mut MemoryConfiguration {
         &mut self.memory_configuration
     }
+
+    // Places stack and heap into RAM leaving `reserved` bytes for static data
+    pub fn plan_memory(&mut self, reserved: u32, stack_size: u32, heap_size: u32) -> Result<()> {
+        let mut planner = LayoutPlanner::new(&self.memory);
+
+        planner.reserve(reserved)?;
+        self.memory_configuration = planner.plan(stack_size, heap_size)?;
+
+        Ok(())
+    }
+
+    // Checks memory regions for overflow, overlaps and zero size
+    pub fn validate_memory(&self) -> Result<()> {
+        memory::validate(&self.memory)
+    }
+
+    // Like validate_memory, also checks Flash and RAM sizes match the catalogue entry
+    pub fn validate_memory_with(&self, info: &McuInfo) -> Result<()> {
+        self.validate_memory()?;
+
+        let flash = total_size(&self.memory, |m| matches!(*m, Memory::Flash { .. }));
+
+        if flash != info.flash_size() {
+            return Err(
+                ErrorKind::MemorySizeMismatch(String::from("FLASH"), info.flash_size(), flash)
+                    .into(),
+            );
+        }
+
+        // Catalogue RAM size includes all SRAM banks and CCM
+        let ram = total_size(&self.memory, |m| m.is_ram());
+
+        if ram != info.ram_size() {
+            return Err(
+                ErrorKind::MemorySizeMismatch(String::from("RAM"), info.ram_size(), ram).into(),
+            );
+        }
+
+        Ok(())
+    }
+
+    // RCC model of the family limited by the maximal frequency of the MCU
+    pub fn clock_tree(&self) -> Result<ClockTree> {
+        match self.platform {
+            Platform::STM32 {
+                ref family,
+                ref line,
+            } => ClockTree::new(family, line, self.frequency),
+            _ => Err(ErrorKind::UnknownClockTree(format!("{:?}", self.platform)).into()),
+        }
+    }
+
+    pub fn configure_clocks(&mut self, request: &ClockRequest) -> Result<()> {
+        self.clock_config = Some(self.clock_tree()?.solve(request)?);
+
+        Ok(())
+    }
+
+    pub fn get_clock_config(&self) -> Option<&ClockConfig> {
+        self.clock_config.as_ref()
+    }
+
+    // Sets configuration made elsewhere, e.g. by hand, after checking it
+    pub fn set_clock_config(&mut self, config: ClockConfig) -> Result<()> {
+        self.clock_tree()?.clocks(&config)?;
+        self.clock_config = Some(config);
+
+        Ok(())
+    }
+
+    // Kernel clock of a peripheral, e.g. for baud rate calculation of "USART1"
+    pub fn peripheral_clock(&self, peripheral: &str) -> Result<Frequency> {
+        let config = self.clock_config.as_ref().ok_or(ErrorKind::NoClockConfiguration)?;
+
+        self.check_peripheral(peripheral)?;
+        self.clock_tree()?.peripheral_clock(config, peripheral)
+    }
+
+    // Counter clock of a timer, twice the APB clock when the bus is prescaled
+    pub fn timer_clock(&self, timer: &str) -> Result<Frequency> {
+        let config = self.clock_config.as_ref().ok_or(ErrorKind::NoClockConfiguration)?;
+
+        self.check_peripheral(timer)?;
+        self.clock_tree()?.timer_clock(config, timer)
+    }
+
+    // The IP list names peripheral kinds such as "USART" or "TIM1_8F0" and not instances,
+    // so only the kind of the instance is checked
+    fn check_peripheral(&self, peripheral: &str) -> Result<()> {
+        let kind = peripheral.trim_end_matches(|c: char| c.is_ascii_digit());
+
+        if kind.is_empty() || !self.periherals.iter().any(|p| p.name.starts_with(kind)) {
+            return Err(ErrorKind::UnknownPeripheral(peripheral.to_string()).into());
+        }
+
+        Ok(())
+    }
+
+    pub fn get_nvic(&self) -> &NvicConfig {
+        &self.nvic
+    }
+
+    pub fn get_nvic_mut(&mut self) -> &mut NvicConfig {
+        &mut self.nvic
+    }
+}
+
+fn total_size<F>(memory: &[Memory], filter: F) -> u32
+where
+    F: Fn(&Memory) -> bool,
+{
+    memory
+        .iter()
+        .filter(|m| filter(m))
+        .fold(0, |total, m| total.saturating_add(m.size()))
 }
 
 #[cfg(test)]
 mod tests {
     // TODO: Check for memory and IPs
     use super::*;
+    use database::McuDatabase;
     use pin::Position;
     // TODO: Test for no file
     #[test]
@@ -220,6 +433,7 @@ mod tests {
             Memory::Flash {
                 start: 0x08000000,
                 size: 32768,
+                sectors: vec![],
             },
             mcu.memory[0]
         );
@@ -230,7 +444,7 @@ mod tests {
             },
             mcu.memory[1]
         );
-        assert_eq!(mcu.frequency, Frequency::MHz(48));
+        assert_eq!(mcu.frequency, Frequency::from_mhz(48));
         assert_eq!(mcu.name, "STM32F030C6Tx");
         assert_eq!(mcu.package, Package::LQFP(48));
         assert_eq!(mcu.ips.len(), 19);
@@ -249,6 +463,7 @@ mod tests {
             Memory::Flash {
                 start: 0x08000000,
                 size: 32768,
+                sectors: vec![],
             },
             mcu_conf.memory[0]
         );
@@ -259,12 +474,130 @@ mod tests {
             },
             mcu_conf.memory[1]
         );
-        assert_eq!(mcu_conf.frequency, Frequency::MHz(48));
+        assert_eq!(mcu_conf.frequency, Frequency::from_mhz(48));
         assert_eq!(mcu_conf.name, "STM32F030C6Tx");
         match mcu_conf.package {
             Package::LQFP(_) => assert!(true),
             _ => assert!(false),
         };
+        assert_eq!(mcu_conf.get_nvic().priority_bits(), 2);
+    }
+
+    #[test]
+    fn mcuconf_missing_nvic() {
+        let sample = Path::new("./samples/STM32F030C6Tx.json");
+        let mcu_conf = MCU::new(sample).unwrap().finish();
+
+        let mut json = serde_json::to_value(&mcu_conf).unwrap();
+        json.as_object_mut().unwrap().remove("nvic");
+
+        let mcu_conf: MCUConf = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(mcu_conf.get_nvic().priority_bits(), 2);
+
+        json["nvic"] = serde_json::from_str(
+            r#"{"priority_bits": 2, "preemption_bits": 4, "irqs": []}"#,
+        )
+        .unwrap();
+        assert!(serde_json::from_value::<MCUConf>(json.clone()).is_err());
+
+        // Cortex-M0 of STM32F030 implements 2 priority bits only
+        json["nvic"] = serde_json::from_str(
+            r#"{"priority_bits": 8, "preemption_bits": 8, "irqs": []}"#,
+        )
+        .unwrap();
+        assert!(serde_json::from_value::<MCUConf>(json.clone()).is_err());
+
+        json["nvic"] = serde_json::from_str(
+            r#"{"priority_bits": 2, "preemption_bits": 0, "irqs": []}"#,
+        )
+        .unwrap();
+        assert!(serde_json::from_value::<MCUConf>(json).is_err());
+    }
+
+    #[test]
+    fn mcuconf_plan_memory() {
+        let sample = Path::new("./samples/STM32F030C6Tx.json");
+        let mut mcu_conf = MCU::new(sample).unwrap().finish();
+
+        assert!(mcu_conf.plan_memory(1024, 0x400, 0x1000).is_err());
+        assert_eq!(mcu_conf.get_memory_configuration().heap_size(), 0);
+
+        mcu_conf.plan_memory(1024, 0x400, 0x200).unwrap();
+        assert_eq!(mcu_conf.get_memory_configuration().stack_addr(), 0x20000C00);
+        assert_eq!(mcu_conf.get_memory_configuration().heap_addr(), 0x20000400);
+    }
+
+    #[test]
+    fn mcuconf_clocks() {
+        let sample = Path::new("./samples/STM32F030C6Tx.json");
+        let mut mcu_conf = MCU::new(sample).unwrap().finish();
+
+        assert!(mcu_conf.peripheral_clock("USART1").is_err());
+        assert!(mcu_conf
+            .configure_clocks(&ClockRequest::new(Frequency::from_mhz(72)))
+            .is_err());
+
+        mcu_conf
+            .configure_clocks(&ClockRequest::new(Frequency::from_mhz(48)).hse(Frequency::from_mhz(8)))
+            .unwrap();
+        assert_eq!(
+            mcu_conf.peripheral_clock("USART1").unwrap(),
+            Frequency::from_mhz(48)
+        );
+        assert_eq!(
+            mcu_conf.timer_clock("TIM3").unwrap(),
+            Frequency::from_mhz(48)
+        );
+
+        let mut config = mcu_conf.get_clock_config().unwrap().clone();
+        config.apb1_prescaler = 2;
+        mcu_conf.set_clock_config(config).unwrap();
+        assert_eq!(
+            mcu_conf.peripheral_clock("USART1").unwrap(),
+            Frequency::from_mhz(24)
+        );
+        assert_eq!(
+            mcu_conf.timer_clock("TIM3").unwrap(),
+            Frequency::from_mhz(48)
+        );
+        assert_eq!(
+            mcu_conf.peripheral_clock("ADC").unwrap(),
+            Frequency::from_mhz(6)
+        );
+
+        match mcu_conf.peripheral_clock("CAN1") {
+            Err(Error(ErrorKind::UnknownPeripheral(name), _)) => assert_eq!(name, "CAN1"),
+            _ => panic!("STM32F030 has no CAN"),
+        }
+    }
+
+    #[test]
+    fn mcuconf_validate_memory() {
+        let sample = Path::new("./samples/STM32F030C6Tx.json");
+        let mut mcu_conf = MCU::new(sample).unwrap().finish();
+        let sample = Path::new("./samples/mcus.json");
+        let db = McuDatabase::new(sample).unwrap();
+        let info = db.find_mcu("STM32F030C6Tx", 32768).unwrap().info;
+
+        assert!(mcu_conf.validate_memory().is_ok());
+        assert!(mcu_conf.validate_memory_with(info).is_ok());
+
+        mcu_conf.get_memory_mut().push(Memory::Ram {
+            start: 0x20000800,
+            size: 4096,
+        });
+        assert!(mcu_conf.validate_memory().is_err());
+
+        mcu_conf.get_memory_mut().pop();
+        let info = db.find_mcu("STM32F030C8Tx", 65536).unwrap().info;
+        match mcu_conf.validate_memory_with(info) {
+            Err(Error(ErrorKind::MemorySizeMismatch(ref name, expected, actual), _)) => {
+                assert_eq!(name, "FLASH");
+                assert_eq!(expected, 65536);
+                assert_eq!(actual, 32768);
+            }
+            _ => assert!(false),
+        }
     }
 
     #[test]
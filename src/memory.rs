@@ -1,8 +1,68 @@
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+use errors::*;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum Interface {
+    QuadSpi,
+    Fmc,
+}
+
+// Access attributes of memory region
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub struct Attributes {
+    pub read: bool,
+    pub write: bool,
+    pub execute: bool,
+    pub cacheable: bool,
+    pub dma: bool,
+}
+
+// Run of equally sized Flash sectors or pages
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct SectorGroup {
+    pub count: u32,
+    pub size: u32,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Sector {
+    pub index: u32,
+    pub start: u32,
+    pub size: u32,
+}
+
+impl Sector {
+    pub fn end(&self) -> u64 {
+        u64::from(self.start) + u64::from(self.size)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub enum Memory {
-    Flash { start: u32, size: u32 },
+    // Empty sector table means unknown erase geometry
+    Flash {
+        start: u32,
+        size: u32,
+        #[serde(default)]
+        sectors: Vec<SectorGroup>,
+    },
     Eeprom { start: u32, size: u32 },
     Ram { start: u32, size: u32 },
+    // Additional SRAM bank such as SRAM2
+    Sram { name: String, start: u32, size: u32 },
+    // Core coupled memory, not reachable by DMA
+    Ccm { start: u32, size: u32 },
+    BackupSram { start: u32, size: u32 },
+    Otp { start: u32, size: u32 },
+    OptionBytes { start: u32, size: u32 },
+    // System memory holding the bootloader
+    SystemRom { start: u32, size: u32 },
+    // Memory mapped window of external memory
+    External {
+        name: String,
+        interface: Interface,
+        start: u32,
+        size: u32,
+    },
 }
 
 impl Memory {
@@ -11,15 +71,19 @@ impl Memory {
             Memory::Flash { start, .. } => start,
             Memory::Eeprom { start, .. } => start,
             Memory::Ram { start, .. } => start,
+            Memory::Sram { start, .. } => start,
+            Memory::Ccm { start, .. } => start,
+            Memory::BackupSram { start, .. } => start,
+            Memory::Otp { start, .. } => start,
+            Memory::OptionBytes { start, .. } => start,
+            Memory::SystemRom { start, .. } => start,
+            Memory::External { start, .. } => start,
         }
     }
 
-    pub fn end(&self) -> u32 {
-        match *self {
-            Memory::Flash { start, size } => start + size,
-            Memory::Eeprom { start, size } => start + size,
-            Memory::Ram { start, size } => start + size,
-        }
+    // End past the last byte, regions may end right at 4 GiB
+    pub fn end(&self) -> u64 {
+        u64::from(self.start()) + u64::from(self.size())
     }
 
     pub fn size(&self) -> u32 {
@@ -27,6 +91,430 @@ impl Memory {
             Memory::Flash { size, .. } => size,
             Memory::Eeprom { size, .. } => size,
             Memory::Ram { size, .. } => size,
+            Memory::Sram { size, .. } => size,
+            Memory::Ccm { size, .. } => size,
+            Memory::BackupSram { size, .. } => size,
+            Memory::Otp { size, .. } => size,
+            Memory::OptionBytes { size, .. } => size,
+            Memory::SystemRom { size, .. } => size,
+            Memory::External { size, .. } => size,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match *self {
+            Memory::Flash { .. } => "FLASH",
+            Memory::Eeprom { .. } => "EEPROM",
+            Memory::Ram { .. } => "RAM",
+            Memory::Sram { ref name, .. } => name,
+            Memory::Ccm { .. } => "CCMRAM",
+            Memory::BackupSram { .. } => "BKPSRAM",
+            Memory::Otp { .. } => "OTP",
+            Memory::OptionBytes { .. } => "OPTION_BYTES",
+            Memory::SystemRom { .. } => "SYSTEM",
+            Memory::External { ref name, .. } => name,
+        }
+    }
+
+    pub fn attributes(&self) -> Attributes {
+        let (read, write, execute, cacheable, dma) = match *self {
+            Memory::Flash { .. } => (true, false, true, true, true),
+            Memory::Eeprom { .. } => (true, true, false, false, true),
+            Memory::Ram { .. } => (true, true, true, true, true),
+            Memory::Sram { .. } => (true, true, true, true, true),
+            Memory::Ccm { .. } => (true, true, false, false, false),
+            Memory::BackupSram { .. } => (true, true, false, false, true),
+            Memory::Otp { .. } => (true, false, false, false, false),
+            Memory::OptionBytes { .. } => (true, false, false, false, false),
+            Memory::SystemRom { .. } => (true, false, true, true, false),
+            Memory::External {
+                interface: Interface::QuadSpi,
+                ..
+            } => (true, false, true, true, true),
+            Memory::External {
+                interface: Interface::Fmc,
+                ..
+            } => (true, true, true, true, true),
+        };
+
+        Attributes {
+            read: read,
+            write: write,
+            execute: execute,
+            cacheable: cacheable,
+            dma: dma,
+        }
+    }
+
+    // Region usable for stack, heap and static data
+    pub fn is_ram(&self) -> bool {
+        matches!(
+            *self,
+            Memory::Ram { .. } | Memory::Sram { .. } | Memory::Ccm { .. }
+        )
+    }
+
+    pub fn contains(&self, addr: u32) -> bool {
+        addr >= self.start() && u64::from(addr) < self.end()
+    }
+
+    // Erasable sectors of Flash in address order, empty for other memories
+    pub fn sectors(&self) -> Vec<Sector> {
+        let mut sectors = vec![];
+
+        if let Memory::Flash {
+            start,
+            sectors: ref groups,
+            ..
+        } = *self
+        {
+            let mut addr = start;
+
+            for group in groups {
+                for _ in 0..group.count {
+                    sectors.push(Sector {
+                        index: sectors.len() as u32,
+                        start: addr,
+                        size: group.size,
+                    });
+                    addr = addr.wrapping_add(group.size);
+                }
+            }
+        }
+
+        sectors
+    }
+
+    // Sectors touched by the address range, these get erased when the range is written
+    pub fn sectors_in(&self, start: u32, size: u32) -> Vec<Sector> {
+        let end = u64::from(start) + u64::from(size);
+
+        self.sectors()
+            .into_iter()
+            .filter(|s| size > 0 && u64::from(s.start) < end && s.end() > u64::from(start))
+            .collect()
+    }
+}
+
+// Checks regions are non-empty, fit the 32-bit address space, don't overlap each other
+// and Flash sector table covers the whole Flash
+pub fn validate(memory: &[Memory]) -> Result<()> {
+    for (idx, region) in memory.iter().enumerate() {
+        if region.size() == 0 {
+            return Err(ErrorKind::EmptyMemoryRegion(region.name().to_string()).into());
+        }
+
+        if region.end() > 1 << 32 {
+            return Err(ErrorKind::MemoryRegionOverflow(region.name().to_string()).into());
+        }
+
+        if let Memory::Flash { ref sectors, .. } = *region {
+            let covered: u64 = sectors
+                .iter()
+                .map(|g| u64::from(g.count) * u64::from(g.size))
+                .sum();
+
+            if !sectors.is_empty() && covered != u64::from(region.size()) {
+                return Err(ErrorKind::MemorySizeMismatch(
+                    String::from("FLASH sectors"),
+                    region.size(),
+                    covered as u32,
+                )
+                .into());
+            }
+        }
+
+        for other in &memory[..idx] {
+            if u64::from(region.start()) < other.end() && u64::from(other.start()) < region.end() {
+                return Err(ErrorKind::MemoryOverlap(
+                    region.name().to_string(),
+                    other.name().to_string(),
+                )
+                .into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Lines with 2 MiB of Flash split into two banks with the layout repeated per bank
+const DUAL_BANK: [&str; 4] = ["STM32F42", "STM32F43", "STM32F469", "STM32F479"];
+
+// Sector table of the part or the whole family such as "STM32F429ZITx" or "STM32F4",
+// uniform pages or mixed sectors in case of F2/F4/F7
+pub fn flash_sectors(part: &str, size: u32) -> Vec<SectorGroup> {
+    const K: u32 = 1024;
+
+    let family = &part[..part.len().min(7)];
+    let dual_bank = size == 2048 * K && DUAL_BANK.iter().any(|line| part.starts_with(line));
+
+    let uniform = |page: u32| {
+        vec![SectorGroup {
+            count: size / page,
+            size: page,
+        }]
+    };
+
+    match family {
+        "STM32F0" if size <= 64 * K => uniform(K),
+        "STM32F0" | "STM32F3" | "STM32L4" => uniform(2 * K),
+        "STM32F1" if size <= 128 * K => uniform(K),
+        "STM32F1" => uniform(2 * K),
+        "STM32L0" => uniform(128),
+        "STM32L1" => uniform(256),
+        "STM32F7" if size > 512 * K => mixed_sectors(size, 32 * K),
+        "STM32F4" if dual_bank => {
+            let mut groups = mixed_sectors(size / 2, 16 * K);
+            groups.extend(mixed_sectors(size / 2, 16 * K));
+            groups
+        }
+        "STM32F2" | "STM32F4" | "STM32F7" => mixed_sectors(size, 16 * K),
+        _ => vec![],
+    }
+}
+
+// Four small sectors followed by one of four times the size and the rest eight times
+fn mixed_sectors(size: u32, small: u32) -> Vec<SectorGroup> {
+    let mut groups = vec![];
+    let mut left = size;
+
+    for &(count, sector) in &[(4, small), (1, small * 4), (u32::MAX, small * 8)] {
+        let count = (left / sector).min(count);
+
+        if count > 0 {
+            groups.push(SectorGroup {
+                count: count,
+                size: sector,
+            });
+            left -= count * sector;
         }
     }
+
+    groups
+}
+
+pub fn find_region(memory: &[Memory], addr: u32) -> Option<&Memory> {
+    memory.iter().find(|m| m.contains(addr))
+}
+
+// Checks whole buffer lies in a single region reachable by DMA
+pub fn is_dma_accessible(memory: &[Memory], addr: u32, size: u32) -> bool {
+    match find_region(memory, addr) {
+        Some(region) => {
+            region.attributes().dma && u64::from(addr) + u64::from(size) <= region.end()
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn stm32f407() -> Vec<Memory> {
+        vec![
+            Memory::Flash {
+                start: 0x08000000,
+                size: 0x100000,
+                sectors: flash_sectors("STM32F4", 0x100000),
+            },
+            Memory::Ram {
+                start: 0x20000000,
+                size: 0x1C000,
+            },
+            Memory::Sram {
+                name: "SRAM2".to_string(),
+                start: 0x2001C000,
+                size: 0x4000,
+            },
+            Memory::Ccm {
+                start: 0x10000000,
+                size: 0x10000,
+            },
+            Memory::SystemRom {
+                start: 0x1FFF0000,
+                size: 0x7800,
+            },
+        ]
+    }
+
+    #[test]
+    fn memory_names() {
+        let memory = stm32f407();
+
+        let names: Vec<&str> = memory.iter().map(|m| m.name()).collect();
+        assert_eq!(names, vec!["FLASH", "RAM", "SRAM2", "CCMRAM", "SYSTEM"]);
+    }
+
+    #[test]
+    fn memory_attributes() {
+        let memory = stm32f407();
+
+        assert_eq!(memory[0].attributes().write, false);
+        assert_eq!(memory[0].attributes().execute, true);
+        assert_eq!(memory[3].attributes().dma, false);
+        assert!(memory[2].is_ram());
+        assert!(memory[3].is_ram());
+        assert!(!memory[4].is_ram());
+
+        let qspi = Memory::External {
+            name: "QSPI".to_string(),
+            interface: Interface::QuadSpi,
+            start: 0x90000000,
+            size: 0x1000000,
+        };
+        assert_eq!(qspi.name(), "QSPI");
+        assert_eq!(qspi.attributes().write, false);
+    }
+
+    #[test]
+    fn dma_accessible() {
+        let memory = stm32f407();
+
+        assert!(is_dma_accessible(&memory, 0x20000000, 0x100));
+        assert!(!is_dma_accessible(&memory, 0x10000000, 0x100));
+        assert!(!is_dma_accessible(&memory, 0x2001FF00, 0x200));
+        assert!(!is_dma_accessible(&memory, 0x40000000, 0x4));
+    }
+
+    #[test]
+    fn find_memory_region() {
+        let memory = stm32f407();
+
+        assert_eq!(find_region(&memory, 0x1000FFFF).unwrap().name(), "CCMRAM");
+        assert_eq!(find_region(&memory, 0x2001C000).unwrap().name(), "SRAM2");
+        assert!(find_region(&memory, 0x10010000).is_none());
+    }
+
+    #[test]
+    fn memory_end() {
+        let memory = Memory::External {
+            name: "FMC".to_string(),
+            interface: Interface::Fmc,
+            start: 0xC0000000,
+            size: 0x40000000,
+        };
+
+        assert_eq!(memory.end(), 0x100000000);
+        assert!(memory.contains(0xFFFFFFFF));
+    }
+
+    #[test]
+    fn validate_ok() {
+        assert!(validate(&stm32f407()).is_ok());
+    }
+
+    #[test]
+    fn validate_errors() {
+        let mut memory = stm32f407();
+        memory.push(Memory::Ccm {
+            start: 0x1000F000,
+            size: 0x2000,
+        });
+        match validate(&memory) {
+            Err(Error(ErrorKind::MemoryOverlap(ref first, ref second), _)) => {
+                assert_eq!(first, "CCMRAM");
+                assert_eq!(second, "CCMRAM");
+            }
+            _ => assert!(false),
+        }
+
+        let empty = vec![Memory::Eeprom {
+            start: 0x08080000,
+            size: 0,
+        }];
+        match validate(&empty) {
+            Err(Error(ErrorKind::EmptyMemoryRegion(ref name), _)) => assert_eq!(name, "EEPROM"),
+            _ => assert!(false),
+        }
+
+        let overflow = vec![Memory::External {
+            name: "FMC".to_string(),
+            interface: Interface::Fmc,
+            start: 0xC0000000,
+            size: 0x40000001,
+        }];
+        match validate(&overflow) {
+            Err(Error(ErrorKind::MemoryRegionOverflow(ref name), _)) => assert_eq!(name, "FMC"),
+            _ => assert!(false),
+        }
+
+        let sectors = vec![Memory::Flash {
+            start: 0x08000000,
+            size: 0x10000,
+            sectors: flash_sectors("STM32F0", 0x8000),
+        }];
+        assert!(validate(&sectors).is_err());
+    }
+
+    #[test]
+    fn sector_geometry() {
+        assert_eq!(
+            flash_sectors("STM32F0", 32768),
+            vec![SectorGroup {
+                count: 32,
+                size: 1024,
+            }]
+        );
+        assert_eq!(flash_sectors("STM32F1", 0x80000)[0].size, 2048);
+        assert_eq!(
+            flash_sectors("STM32F4", 0x60000),
+            vec![
+                SectorGroup {
+                    count: 4,
+                    size: 0x4000,
+                },
+                SectorGroup {
+                    count: 1,
+                    size: 0x10000,
+                },
+                SectorGroup {
+                    count: 2,
+                    size: 0x20000,
+                },
+            ]
+        );
+        assert_eq!(flash_sectors("STM32F429ZITx", 0x200000).len(), 6);
+        assert_eq!(
+            flash_sectors("STM32F413ZHTx", 0x180000),
+            vec![
+                SectorGroup {
+                    count: 4,
+                    size: 0x4000,
+                },
+                SectorGroup {
+                    count: 1,
+                    size: 0x10000,
+                },
+                SectorGroup {
+                    count: 11,
+                    size: 0x20000,
+                },
+            ]
+        );
+        assert_eq!(flash_sectors("STM32F7", 0x100000)[2].size, 0x40000);
+        assert!(flash_sectors("STM32H7", 0x100000).is_empty());
+    }
+
+    #[test]
+    fn sectors_in_range() {
+        let memory = stm32f407();
+        let flash = &memory[0];
+
+        let sectors = flash.sectors();
+        assert_eq!(sectors.len(), 12);
+        assert_eq!(sectors[4].start, 0x08010000);
+        assert_eq!(sectors[11].end(), 0x08100000);
+
+        let touched = flash.sectors_in(0x08003000, 0x2000);
+        assert_eq!(touched.len(), 2);
+        assert_eq!(touched[0].index, 0);
+        assert_eq!(touched[1].index, 1);
+
+        assert_eq!(flash.sectors_in(0x08010000, 0x10000).len(), 1);
+        assert!(flash.sectors_in(0x08010000, 0).is_empty());
+        assert!(memory[1].sectors().is_empty());
+    }
 }
@@ -0,0 +1,292 @@
+use errors::*;
+use irqs::Interrupt;
+use mcu::{ARMCore, Core};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct IrqConfig {
+    irqn: String,
+    preemption: u8,
+    sub_priority: u8,
+}
+
+impl IrqConfig {
+    pub fn irqn(&self) -> &str {
+        &self.irqn
+    }
+
+    pub fn preemption(&self) -> u8 {
+        self.preemption
+    }
+
+    pub fn sub_priority(&self) -> u8 {
+        self.sub_priority
+    }
+}
+
+// NvicConfig class
+//
+//    Holds enabled interrupts with their priorities. The implemented priority bits are
+//    split by the priority grouping into preemption and sub-priority bits.
+//
+//    \sa Interrupt, MCUConf
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NvicConfig {
+    priority_bits: u8,
+    preemption_bits: u8,
+    irqs: Vec<IrqConfig>,
+}
+
+impl NvicConfig {
+    pub fn new(core: &Core) -> NvicConfig {
+        let priority_bits = match *core {
+            Core::ARM(ARMCore::CortexM0) | Core::ARM(ARMCore::CortexM0Plus) => 2,
+            Core::ARM(_) => 4,
+            _ => 0,
+        };
+
+        NvicConfig {
+            priority_bits: priority_bits,
+            preemption_bits: priority_bits,
+            irqs: Vec::new(),
+        }
+    }
+
+    pub fn priority_bits(&self) -> u8 {
+        self.priority_bits
+    }
+
+    pub fn preemption_bits(&self) -> u8 {
+        self.preemption_bits
+    }
+
+    pub fn sub_priority_bits(&self) -> u8 {
+        self.priority_bits.saturating_sub(self.preemption_bits)
+    }
+
+    pub fn irqs(&self) -> &Vec<IrqConfig> {
+        &self.irqs
+    }
+
+    // Splits priority bits, Cortex-M0 has no grouping so all bits are preemption bits
+    pub fn set_priority_grouping(&mut self, preemption_bits: u8) -> Result<()> {
+        self.check_grouping(preemption_bits)?;
+
+        let sub_priority_bits = self.priority_bits - preemption_bits;
+
+        for irq in &self.irqs {
+            if !fits(irq.preemption, preemption_bits) || !fits(irq.sub_priority, sub_priority_bits)
+            {
+                return Err(ErrorKind::InvalidPriorityGrouping(preemption_bits).into());
+            }
+        }
+
+        self.preemption_bits = preemption_bits;
+
+        Ok(())
+    }
+
+    pub fn enable(
+        &mut self,
+        interrupt: &Interrupt,
+        preemption: u8,
+        sub_priority: u8,
+    ) -> Result<()> {
+        if !interrupt.enableable {
+            return Err(ErrorKind::InterruptNotEnableable(interrupt.irqn.clone()).into());
+        }
+
+        self.check_priority(&interrupt.irqn, preemption, sub_priority)?;
+
+        match self.irqs.iter().position(|irq| irq.irqn == interrupt.irqn) {
+            Some(idx) => {
+                self.irqs[idx].preemption = preemption;
+                self.irqs[idx].sub_priority = sub_priority;
+            }
+            None => self.irqs.push(IrqConfig {
+                irqn: interrupt.irqn.clone(),
+                preemption: preemption,
+                sub_priority: sub_priority,
+            }),
+        }
+
+        Ok(())
+    }
+
+    pub fn disable(&mut self, irqn: &str) {
+        self.irqs.retain(|irq| irq.irqn != irqn);
+    }
+
+    pub fn is_enabled(&self, irqn: &str) -> bool {
+        self.irqs.iter().any(|irq| irq.irqn == irqn)
+    }
+
+    pub fn get(&self, irqn: &str) -> Option<&IrqConfig> {
+        self.irqs.iter().find(|irq| irq.irqn == irqn)
+    }
+
+    pub fn set_priority(&mut self, irqn: &str, preemption: u8, sub_priority: u8) -> Result<()> {
+        self.check_priority(irqn, preemption, sub_priority)?;
+
+        match self.irqs.iter_mut().find(|irq| irq.irqn == irqn) {
+            Some(irq) => {
+                irq.preemption = preemption;
+                irq.sub_priority = sub_priority;
+                Ok(())
+            }
+            None => Err(ErrorKind::InterruptNotEnabled(irqn.to_string()).into()),
+        }
+    }
+
+    // Value of the priority register, implemented bits are the most significant ones
+    pub fn encoded_priority(&self, irqn: &str) -> Option<u8> {
+        let shift = 8u8.checked_sub(self.priority_bits)?;
+
+        self.get(irqn).map(|irq| {
+            let priority = (u16::from(irq.preemption) << self.sub_priority_bits())
+                | u16::from(irq.sub_priority);
+            (priority << shift) as u8
+        })
+    }
+
+    // Checks the priority bits, the grouping and all priorities, useful after
+    // deserialization
+    pub fn validate(&self) -> Result<()> {
+        if self.priority_bits > 8 {
+            return Err(ErrorKind::InvalidPriorityBits(self.priority_bits).into());
+        }
+
+        self.check_grouping(self.preemption_bits)?;
+
+        for irq in &self.irqs {
+            self.check_priority(&irq.irqn, irq.preemption, irq.sub_priority)?;
+        }
+
+        Ok(())
+    }
+
+    fn check_grouping(&self, preemption_bits: u8) -> Result<()> {
+        if preemption_bits > self.priority_bits || (self.priority_bits == 2 && preemption_bits != 2)
+        {
+            return Err(ErrorKind::InvalidPriorityGrouping(preemption_bits).into());
+        }
+
+        Ok(())
+    }
+
+    fn check_priority(&self, irqn: &str, preemption: u8, sub_priority: u8) -> Result<()> {
+        if !fits(preemption, self.preemption_bits) || !fits(sub_priority, self.sub_priority_bits())
+        {
+            return Err(
+                ErrorKind::InvalidPriority(irqn.to_string(), preemption, sub_priority).into(),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+fn fits(value: u8, bits: u8) -> bool {
+    u16::from(value) < (1u16 << bits)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use irqs::IRQ;
+    use serde_json;
+
+    fn interrupt(value: &str) -> Interrupt {
+        Interrupt::new(&IRQ {
+            desc: String::new(),
+            value: value.to_string(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn priority_bits() {
+        assert_eq!(
+            NvicConfig::new(&Core::ARM(ARMCore::CortexM0)).priority_bits(),
+            2
+        );
+        assert_eq!(
+            NvicConfig::new(&Core::ARM(ARMCore::CortexM0Plus)).priority_bits(),
+            2
+        );
+        assert_eq!(
+            NvicConfig::new(&Core::ARM(ARMCore::CortexM4)).priority_bits(),
+            4
+        );
+    }
+
+    #[test]
+    fn enable_m0() {
+        let mut nvic = NvicConfig::new(&Core::ARM(ARMCore::CortexM0));
+        let usart = interrupt("USART1_IRQn:Y:USART1::");
+
+        assert!(nvic.enable(&usart, 3, 0).is_ok());
+        assert!(nvic.is_enabled("USART1_IRQn"));
+        assert_eq!(nvic.encoded_priority("USART1_IRQn"), Some(0xC0));
+
+        assert!(nvic.enable(&usart, 4, 0).is_err());
+        assert!(nvic.enable(&usart, 0, 1).is_err());
+        assert!(nvic.set_priority_grouping(1).is_err());
+
+        nvic.disable("USART1_IRQn");
+        assert!(!nvic.is_enabled("USART1_IRQn"));
+    }
+
+    #[test]
+    fn enable_not_enableable() {
+        let mut nvic = NvicConfig::new(&Core::ARM(ARMCore::CortexM4));
+        let hardfault = interrupt("HardFault_IRQn:N,W1:::");
+
+        assert!(nvic.enable(&hardfault, 0, 0).is_err());
+    }
+
+    #[test]
+    fn priority_grouping() {
+        let mut nvic = NvicConfig::new(&Core::ARM(ARMCore::CortexM4));
+        let tim = interrupt("TIM2_IRQn:Y:TIM2:HAL_TIM_IRQHandler:");
+
+        assert!(nvic.set_priority_grouping(2).is_ok());
+        assert_eq!(nvic.sub_priority_bits(), 2);
+
+        assert!(nvic.enable(&tim, 3, 2).is_ok());
+        assert_eq!(nvic.encoded_priority("TIM2_IRQn"), Some(0xE0));
+        assert!(nvic.set_priority("TIM2_IRQn", 4, 0).is_err());
+
+        // Sub-priority 2 doesn't fit with grouping of 4 preemption bits
+        assert!(nvic.set_priority_grouping(4).is_err());
+        assert!(nvic.set_priority_grouping(5).is_err());
+        assert!(nvic.validate().is_ok());
+    }
+
+    #[test]
+    fn validate_loaded() {
+        let load = |json: &str| serde_json::from_str::<NvicConfig>(json).unwrap();
+
+        // Cortex-M0 has no grouping
+        let nvic = load(r#"{"priority_bits": 2, "preemption_bits": 1, "irqs": []}"#);
+        assert!(nvic.validate().is_err());
+
+        let nvic = load(r#"{"priority_bits": 9, "preemption_bits": 9, "irqs": []}"#);
+        assert!(nvic.validate().is_err());
+        assert_eq!(nvic.encoded_priority("TIM2_IRQn"), None);
+
+        let nvic = load(
+            r#"{"priority_bits": 8, "preemption_bits": 8,
+                "irqs": [{"irqn": "TIM2_IRQn", "preemption": 255, "sub_priority": 0}]}"#,
+        );
+        assert!(nvic.validate().is_ok());
+        assert_eq!(nvic.encoded_priority("TIM2_IRQn"), Some(0xFF));
+    }
+
+    #[test]
+    fn set_priority_disabled() {
+        let mut nvic = NvicConfig::new(&Core::ARM(ARMCore::CortexM3));
+
+        assert!(nvic.set_priority("TIM2_IRQn", 1, 0).is_err());
+    }
+}
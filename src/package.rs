@@ -1,6 +1,14 @@
+use std::fmt;
+use std::str::FromStr;
+
 use regex::Regex;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+use errors::*;
+
+// Text allowed after the pin count, SO width or ST package variant
+const SUFFIXES: [&str; 3] = ["N", "-L", "-SMPS"];
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub enum Package {
     LQFP(u16),
     TSSOP(u16),
@@ -11,36 +19,27 @@ pub enum Package {
     EWLCSP(u16),
     UFBGA(u16),
     LFBGA(u16),
+    // Narrow small outline such as SO8N
+    SO(u16),
+    SOIC(u16),
+    DIP(u16),
+    QFN(u16),
+    TQFP(u16),
     Unknown(u16),
+    // Name the package doesn't print by itself such as "PDIP28" or "WLCSP49-L", kept
+    // to print it back
+    Named(Box<Package>, String),
 }
 
 impl Package {
-    // TODO: Return error
-    pub fn new(package: &str) -> Package {
-        lazy_static! {
-            static ref RE :Regex = Regex::new(r"([[:alpha:]]*)(\d*)").unwrap();
-        }
-
-        let caps = RE.captures(package).unwrap();
-
-        let count = caps.get(2).unwrap().as_str().parse::<u16>().unwrap();
-
-        match caps.get(1).unwrap().as_str() {
-            "LQFP" => Package::LQFP(count),
-            "TSSOP" => Package::TSSOP(count),
-            "WLCSP" => Package::WLCSP(count),
-            "UFQFPN" => Package::UFQFPN(count),
-            "TFBGA" => Package::TFBGA(count),
-            "VFQFPN" => Package::VFQFPN(count),
-            "EWLCSP" => Package::EWLCSP(count),
-            "UFBGA" => Package::UFBGA(count),
-            "LFBGA" => Package::LFBGA(count),
-            &_ => Package::Unknown(count),
-        }
+    #[deprecated(note = "use str::parse()")]
+    pub fn new(package: &str) -> Result<Package> {
+        package.parse()
     }
 
     pub fn is_grid(&self) -> bool {
         match *self {
+            Package::Named(ref package, _) => package.is_grid(),
             Package::UFBGA(_) => true,
             Package::TFBGA(_) => true,
             Package::EWLCSP(_) => true,
@@ -50,6 +49,39 @@ impl Package {
         }
     }
 
+    // Pins on two opposite sides
+    pub fn is_dual(&self) -> bool {
+        match *self {
+            Package::Named(ref package, _) => package.is_dual(),
+            Package::TSSOP(_) => true,
+            Package::SO(_) => true,
+            Package::SOIC(_) => true,
+            Package::DIP(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn family(&self) -> &str {
+        match *self {
+            Package::LQFP(_) => "LQFP",
+            Package::TSSOP(_) => "TSSOP",
+            Package::WLCSP(_) => "WLCSP",
+            Package::UFQFPN(_) => "UFQFPN",
+            Package::TFBGA(_) => "TFBGA",
+            Package::VFQFPN(_) => "VFQFPN",
+            Package::EWLCSP(_) => "EWLCSP",
+            Package::UFBGA(_) => "UFBGA",
+            Package::LFBGA(_) => "LFBGA",
+            Package::SO(_) => "SO",
+            Package::SOIC(_) => "SOIC",
+            Package::DIP(_) => "DIP",
+            Package::QFN(_) => "QFN",
+            Package::TQFP(_) => "TQFP",
+            Package::Unknown(_) => "Unknown",
+            Package::Named(ref package, _) => package.family(),
+        }
+    }
+
     pub fn pins(&self) -> u16 {
         match *self {
             Package::LQFP(count) => count,
@@ -61,7 +93,78 @@ impl Package {
             Package::EWLCSP(count) => count,
             Package::UFBGA(count) => count,
             Package::LFBGA(count) => count,
+            Package::SO(count) => count,
+            Package::SOIC(count) => count,
+            Package::DIP(count) => count,
+            Package::QFN(count) => count,
+            Package::TQFP(count) => count,
             Package::Unknown(count) => count,
+            Package::Named(ref package, _) => package.pins(),
+        }
+    }
+}
+
+// Family followed by the pin count such as "LQFP48", parsed names print back as they
+// were spelled
+impl fmt::Display for Package {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Package::Named(_, ref name) => write!(f, "{}", name),
+            _ => write!(f, "{}{}", self.family(), self.pins()),
+        }
+    }
+}
+
+// Parses package names such as "LQFP48", "SO8N", "PDIP28" or "WLCSP49-L". Only the
+// suffixes of SUFFIXES may follow the pin count.
+impl FromStr for Package {
+    type Err = Error;
+
+    fn from_str(package: &str) -> Result<Package> {
+        lazy_static! {
+            static ref RE: Regex = Regex::new(r"^([[:alpha:]]+)(\d+)(.*)$").unwrap();
+        }
+
+        let caps = match RE.captures(package.trim()) {
+            Some(caps) => caps,
+            None => return Err(ErrorKind::InvalidPackage(package.to_string()).into()),
+        };
+
+        let count = caps[2]
+            .parse::<u16>()
+            .map_err(|_| Error::from(ErrorKind::InvalidPackage(package.to_string())))?;
+
+        let family = caps[1].to_uppercase();
+        let suffix = caps[3].to_uppercase();
+
+        if !suffix.is_empty() && !SUFFIXES.contains(&suffix.as_str()) {
+            return Err(ErrorKind::InvalidPackage(package.to_string()).into());
+        }
+
+        let parsed = match family.as_str() {
+            "LQFP" => Package::LQFP(count),
+            "TSSOP" => Package::TSSOP(count),
+            "WLCSP" => Package::WLCSP(count),
+            "UFQFPN" => Package::UFQFPN(count),
+            "TFBGA" => Package::TFBGA(count),
+            "VFQFPN" => Package::VFQFPN(count),
+            "EWLCSP" => Package::EWLCSP(count),
+            "UFBGA" => Package::UFBGA(count),
+            "LFBGA" => Package::LFBGA(count),
+            "SO" => Package::SO(count),
+            "SOIC" => Package::SOIC(count),
+            "DIP" | "PDIP" => Package::DIP(count),
+            "QFN" | "VQFN" | "MLF" => Package::QFN(count),
+            "TQFP" => Package::TQFP(count),
+            _ => return Err(ErrorKind::UnknownPackage(caps[1].to_string()).into()),
+        };
+
+        let name = format!("{}{}{}", family, count, suffix);
+
+        if name.eq_ignore_ascii_case(&parsed.to_string()) {
+            Ok(parsed)
+        } else {
+            Ok(Package::Named(Box::new(parsed), name))
         }
     }
 }
@@ -70,12 +173,14 @@ impl Package {
 mod tests {
 
     use super::*;
+    use serde_json::{self, Value};
+    use std::fs::File;
 
     #[test]
     fn package_lqfp48() {
         let package_name = "LQFP48";
 
-        let package = Package::new(package_name);
+        let package: Package = package_name.parse().unwrap();
 
         assert_eq!(false, package.is_grid());
         assert_eq!(48, package.pins());
@@ -85,17 +190,124 @@ mod tests {
     fn package_tfbga() {
         let package_name = "TFBGA144";
 
-        let package = Package::new(package_name);
+        let package: Package = package_name.parse().unwrap();
 
         assert_eq!(true, package.is_grid());
         assert_eq!(144, package.pins());
+        assert_eq!("TFBGA", package.family());
     }
 
     #[test]
-    #[should_panic]
     fn package_fail() {
-        let package_name = "";
+        assert!("".parse::<Package>().is_err());
+        assert!("LQFP".parse::<Package>().is_err());
+        assert!("48".parse::<Package>().is_err());
+        assert!("LQFP99999".parse::<Package>().is_err());
+        assert!("LQFP48foo".parse::<Package>().is_err());
+        assert!("WLCSP49-X".parse::<Package>().is_err());
+
+        match "XQFP48".parse::<Package>() {
+            Err(Error(ErrorKind::UnknownPackage(family), _)) => assert_eq!(family, "XQFP"),
+            _ => panic!("XQFP48 is not a known package"),
+        }
+    }
+
+    #[test]
+    fn package_families() {
+        assert_eq!("SO8".parse::<Package>().unwrap(), Package::SO(8));
+        assert_eq!("SOIC20".parse::<Package>().unwrap(), Package::SOIC(20));
+        assert_eq!("TQFP32".parse::<Package>().unwrap(), Package::TQFP(32));
+        assert_eq!("TSSOP20 ".parse::<Package>().unwrap(), Package::TSSOP(20));
+        assert_eq!("UFQFPN28".parse::<Package>().unwrap(), Package::UFQFPN(28));
+        assert!(Package::SO(8).is_dual());
+        assert!(!Package::QFN(20).is_dual());
 
-        let package = Package::new(package_name);
+        let pdip: Package = "PDIP28".parse().unwrap();
+        assert_eq!(
+            pdip,
+            Package::Named(Box::new(Package::DIP(28)), "PDIP28".to_string())
+        );
+        assert_eq!(pdip.family(), "DIP");
+        assert_eq!(pdip.pins(), 28);
+        assert!(pdip.is_dual());
+
+        let wlcsp: Package = "WLCSP49-L".parse().unwrap();
+        assert_eq!(wlcsp.family(), "WLCSP");
+        assert!(wlcsp.is_grid());
+        assert_eq!("vqfn20".parse::<Package>().unwrap().to_string(), "VQFN20");
+    }
+
+    #[test]
+    fn package_display() {
+        let packages = [
+            "LQFP48",
+            "SO8",
+            "SO8N",
+            "DIP28",
+            "PDIP28",
+            "QFN32",
+            "VQFN20",
+            "MLF28",
+            "WLCSP49-L",
+            "UFQFPN48-SMPS",
+        ];
+
+        for package in &packages {
+            assert_eq!(package.parse::<Package>().unwrap().to_string(), *package);
+        }
+
+        // Unknown only comes from saved projects, it isn't a family name
+        match "Unknown12".parse::<Package>() {
+            Err(Error(ErrorKind::UnknownPackage(family), _)) => assert_eq!(family, "Unknown"),
+            _ => panic!("Unknown isn't a package family"),
+        }
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn package_new() {
+        assert_eq!(Package::new("LQFP48").unwrap(), Package::LQFP(48));
+        match Package::new("XQFP48") {
+            Err(Error(ErrorKind::UnknownPackage(family), _)) => assert_eq!(family, "XQFP"),
+            _ => panic!("XQFP48 parsed as package"),
+        }
+    }
+
+    // Every package of the MCU database parses and prints back the same
+    #[test]
+    fn package_database() {
+        fn collect(value: &Value, packages: &mut Vec<String>) {
+            match *value {
+                Value::Object(ref map) => {
+                    for (key, value) in map {
+                        match (key.as_str(), value) {
+                            ("Package", &Value::String(ref package)) => {
+                                packages.push(package.clone())
+                            }
+                            _ => collect(value, packages),
+                        }
+                    }
+                }
+                Value::Array(ref values) => {
+                    for value in values {
+                        collect(value, packages);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        let file = File::open("./samples/mcus.json").unwrap();
+        let mcus: Value = serde_json::from_reader(file).unwrap();
+
+        let mut packages = vec![];
+        collect(&mcus, &mut packages);
+        assert!(!packages.is_empty());
+
+        for package in &packages {
+            let parsed: Package = package.parse().unwrap();
+            assert_ne!(parsed.family(), "Unknown");
+            assert_eq!(parsed.to_string(), *package);
+        }
     }
 }
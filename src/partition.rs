@@ -0,0 +1,236 @@
+use errors::*;
+use memory::{Memory, Sector};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Partition {
+    pub name: String,
+    pub start: u32,
+    pub size: u32,
+}
+
+// FlashPartitioner class
+//
+//    Splits Flash into partitions made of whole sectors so each of them can be erased
+//    on its own. Bootloader is taken from the start of Flash, storage such as EEPROM
+//    emulation or configuration from the end, application gets the rest.
+//
+//    \sa Memory, LinkerScript
+#[derive(Debug)]
+pub struct FlashPartitioner {
+    sectors: Vec<Sector>,
+    // Free sectors span from low to high
+    low: usize,
+    high: usize,
+    partitions: Vec<Partition>,
+}
+
+impl FlashPartitioner {
+    pub fn new(flash: &Memory) -> Result<FlashPartitioner> {
+        let sectors = flash.sectors();
+
+        if sectors.is_empty() {
+            return Err(ErrorKind::UnknownFlashGeometry.into());
+        }
+
+        Ok(FlashPartitioner {
+            low: 0,
+            high: sectors.len(),
+            sectors: sectors,
+            partitions: vec![],
+        })
+    }
+
+    pub fn free(&self) -> u32 {
+        self.sectors[self.low..self.high]
+            .iter()
+            .map(|s| s.size)
+            .sum()
+    }
+
+    // Reserves sectors at the start of free Flash
+    pub fn reserve_start(&mut self, name: &str, size: u32) -> Result<&Partition> {
+        check_size(name, size)?;
+
+        let count = self.count_low(size).ok_or_else(|| overflow(name, size))?;
+
+        self.low += count;
+
+        Ok(self.push(name, self.low - count, self.low))
+    }
+
+    // Reserves sectors at the end of free Flash
+    pub fn reserve_end(&mut self, name: &str, size: u32) -> Result<&Partition> {
+        check_size(name, size)?;
+
+        let count = self
+            .count_high(self.high, size)
+            .ok_or_else(|| overflow(name, size))?;
+
+        self.high -= count;
+
+        Ok(self.push(name, self.high, self.high + count))
+    }
+
+    pub fn bootloader(&mut self, size: u32) -> Result<&Partition> {
+        self.reserve_start("BOOTLOADER", size)
+    }
+
+    // EEPROM emulation swaps data between two sector runs, each has to hold all of it
+    pub fn eeprom_emulation(&mut self, size: u32) -> Result<&Partition> {
+        let name = "EEPROM_EMULATION";
+        check_size(name, size)?;
+
+        let first = self
+            .count_high(self.high, size)
+            .ok_or_else(|| overflow(name, size))?;
+        let second = self
+            .count_high(self.high - first, size)
+            .ok_or_else(|| overflow(name, size))?;
+
+        self.high -= first + second;
+
+        Ok(self.push(name, self.high, self.high + first + second))
+    }
+
+    pub fn config(&mut self, size: u32) -> Result<&Partition> {
+        self.reserve_end("CONFIG", size)
+    }
+
+    // Reserved partitions in address order, free sectors left form APP partition
+    pub fn finish(mut self) -> Vec<Partition> {
+        if self.low < self.high {
+            let (low, high) = (self.low, self.high);
+            self.push("APP", low, high);
+        }
+
+        let mut partitions = self.partitions;
+        partitions.sort_by_key(|p| p.start);
+        partitions
+    }
+
+    // Number of free sectors from the low end holding the size
+    fn count_low(&self, size: u32) -> Option<usize> {
+        let mut total = 0u64;
+
+        for (count, sector) in self.sectors[self.low..self.high].iter().enumerate() {
+            total += u64::from(sector.size);
+
+            if total >= u64::from(size) {
+                return Some(count + 1);
+            }
+        }
+
+        None
+    }
+
+    // Number of free sectors downwards from high holding the size
+    fn count_high(&self, high: usize, size: u32) -> Option<usize> {
+        let mut total = 0u64;
+
+        for (count, sector) in self.sectors[self.low..high].iter().rev().enumerate() {
+            total += u64::from(sector.size);
+
+            if total >= u64::from(size) {
+                return Some(count + 1);
+            }
+        }
+
+        None
+    }
+
+    fn push(&mut self, name: &str, first: usize, last: usize) -> &Partition {
+        self.partitions.push(Partition {
+            name: name.to_string(),
+            start: self.sectors[first].start,
+            size: self.sectors[first..last].iter().map(|s| s.size).sum(),
+        });
+
+        self.partitions.last().unwrap()
+    }
+}
+
+fn overflow(name: &str, size: u32) -> Error {
+    ErrorKind::MemoryOverflow(name.to_string(), size).into()
+}
+
+// Empty partition would still take a whole sector
+fn check_size(name: &str, size: u32) -> Result<()> {
+    if size == 0 {
+        return Err(ErrorKind::EmptyMemoryRegion(name.to_string()).into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use memory::flash_sectors;
+
+    fn flash(family: &str, size: u32) -> Memory {
+        Memory::Flash {
+            start: 0x08000000,
+            size: size,
+            sectors: flash_sectors(family, size),
+        }
+    }
+
+    #[test]
+    fn partition_f4() {
+        let mut partitioner = FlashPartitioner::new(&flash("STM32F4", 0x80000)).unwrap();
+
+        assert_eq!(partitioner.bootloader(0x5000).unwrap().size, 0x8000);
+        assert_eq!(partitioner.eeprom_emulation(0x1000).unwrap().size, 0x40000);
+        assert_eq!(partitioner.free(), 0x38000);
+
+        let partitions = partitioner.finish();
+        assert_eq!(
+            partitions,
+            vec![
+                Partition {
+                    name: "BOOTLOADER".to_string(),
+                    start: 0x08000000,
+                    size: 0x8000,
+                },
+                Partition {
+                    name: "APP".to_string(),
+                    start: 0x08008000,
+                    size: 0x38000,
+                },
+                Partition {
+                    name: "EEPROM_EMULATION".to_string(),
+                    start: 0x08040000,
+                    size: 0x40000,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn partition_f0() {
+        let mut partitioner = FlashPartitioner::new(&flash("STM32F0", 32768)).unwrap();
+
+        assert_eq!(partitioner.bootloader(4000).unwrap().size, 4096);
+        assert_eq!(partitioner.config(100).unwrap().start, 0x08007C00);
+        assert!(partitioner.reserve_end("LOG", 0x8000).is_err());
+        assert_eq!(partitioner.free(), 27 * 1024);
+        assert_eq!(partitioner.finish().len(), 3);
+    }
+
+    #[test]
+    fn partition_zero_size() {
+        let mut partitioner = FlashPartitioner::new(&flash("STM32F0", 32768)).unwrap();
+
+        assert!(partitioner.bootloader(0).is_err());
+        assert!(partitioner.config(0).is_err());
+        assert!(partitioner.eeprom_emulation(0).is_err());
+        assert_eq!(partitioner.free(), 32768);
+        assert_eq!(partitioner.finish().len(), 1);
+    }
+
+    #[test]
+    fn partition_unknown_geometry() {
+        assert!(FlashPartitioner::new(&flash("STM32H7", 0x20000)).is_err());
+    }
+}
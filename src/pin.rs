@@ -1,3 +1,11 @@
+use std::fmt;
+use std::str::FromStr;
+
+use errors::*;
+use geometry::{row_label, row_number};
+use gpio::GpioConfig;
+use signal::Signal;
+
 #[derive(Debug)]
 pub struct PinBuilder<'a> {
     pin_type: &'a str,
@@ -28,6 +36,17 @@ impl<'a> PinBuilder<'a> {
         }
     }
 
+    // Signals of pin in reset state with no signal selected
+    pub fn reset_signals(&mut self, signals: Vec<String>) -> PinBuilder<'a> {
+        PinBuilder {
+            pin_type: self.pin_type,
+            name: self.name,
+            position: self.position,
+            signals: Some(signals),
+            current: None,
+        }
+    }
+
     pub fn finish(self) -> Pin {
         let current = match self.current {
             Some(idx) => match &self.signals {
@@ -62,17 +81,28 @@ impl<'a> PinBuilder<'a> {
                 position: self.position,
             },
             "I/O" => {
-                return Pin::IO {
+                let signals: Vec<Signal> = match self.signals {
+                    Some(s) => s.iter().map(|name| Signal::new(name)).collect(),
+                    None => Vec::new(),
+                };
+
+                let config = match current {
+                    Some(idx) => GpioConfig::for_signal(signals[idx].kind()),
+                    None => GpioConfig::default(),
+                };
+
+                // Pin built with a selected signal is out of reset state
+                Pin::IO {
                     name: String::from(self.name),
                     position: self.position,
                     params: Box::new(IOPin {
-                        reset: true,
+                        reset: current.is_none(),
                         label: String::new(),
-                        signals: match self.signals {
-                            Some(s) => s,
-                            None => Vec::new(),
-                        },
+                        signals: signals,
                         current: current,
+                        locked: false,
+                        required: None,
+                        config: config,
                     }),
                 }
             }
@@ -84,62 +114,199 @@ impl<'a> PinBuilder<'a> {
     }
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
 pub enum Position {
     Linear(u16),
     Grid(u8, u8),
 }
 
+// Pin number as printed in datasheets, "12" for linear packages and "F7" for grids
+// with rows and columns counted from 1
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Position::Linear(number) => write!(f, "{}", number),
+            Position::Grid(row, column) => write!(f, "{}{}", row_label(row), column),
+        }
+    }
+}
+
+// Parses pin numbers such as "12" and ball names such as "F7"
+impl FromStr for Position {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Position> {
+        let invalid = || Error::from(ErrorKind::InvalidPosition(s.to_string()));
+
+        let split = s.find(|c: char| c.is_ascii_digit()).ok_or_else(invalid)?;
+        let (row, number) = s.split_at(split);
+
+        if row.is_empty() {
+            return number.parse().map(Position::Linear).map_err(|_| invalid());
+        }
+
+        let row = row_number(row).ok_or_else(invalid)?;
+        let column = number.parse().map_err(|_| invalid())?;
+
+        Ok(Position::Grid(row, column))
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct IOPin {
     reset: bool,
     label: String,
-    signals: Vec<String>,
+    signals: Vec<Signal>,
     current: Option<usize>,
+    // Pin routed on the PCB, its signal can't change
+    #[serde(default)]
+    locked: bool,
+    // Signal the user wants on this pin
+    #[serde(default)]
+    required: Option<String>,
+    #[serde(default)]
+    config: GpioConfig,
 }
 
 impl IOPin {
+    // Back to reset state, the selected signal and its GPIO configuration are dropped.
+    // Locked pin stays untouched and keeps its signal
     pub fn reset(&mut self) {
+        if self.locked {
+            return;
+        }
+
         self.reset = true;
+        self.current = None;
+        self.config = GpioConfig::default();
+    }
+
+    pub fn lock(&mut self) {
+        self.locked = true;
+    }
+
+    pub fn unlock(&mut self) {
+        self.locked = false;
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    // Requires the signal on this pin, fails when the pin doesn't have it
+    pub fn require_signal(&mut self, signal: &str) -> bool {
+        if self.find_signal(signal).is_none() {
+            return false;
+        }
+
+        self.required = Some(signal.to_string());
+        true
+    }
+
+    pub fn clear_required(&mut self) {
+        self.required = None;
+    }
+
+    pub fn required_signal(&self) -> Option<&str> {
+        self.required.as_deref()
     }
 
     pub fn is_reset(&self) -> bool {
         self.reset
     }
 
-    pub fn set_label(&mut self, label: &str) {
+    // Locked pin keeps its label and false is returned
+    pub fn set_label(&mut self, label: &str) -> bool {
+        if self.locked {
+            return false;
+        }
+
         self.label = label.to_string();
+        true
     }
 
     pub fn label(&self) -> &str {
         &self.label
     }
 
-    pub fn signals(&self) -> &Vec<String> {
+    pub fn signals(&self) -> &Vec<Signal> {
         &self.signals
     }
 
+    pub fn find_signal(&self, signal: &str) -> Option<&Signal> {
+        self.signals.iter().find(|s| s.name() == signal)
+    }
+
+    // Locked pin keeps its signal and false is returned
     pub fn select_signal(&mut self, signal: &str) -> bool {
-        let item = self.signals.iter().position(|r| r.as_str() == signal);
+        if self.locked {
+            return false;
+        }
+
+        let item = self.signals.iter().position(|s| s.name() == signal);
 
         match item {
             Some(idx) => {
+                self.reset = false;
                 self.current = Some(idx);
+                self.config = GpioConfig::for_signal(self.signals[idx].kind());
                 true
             }
             None => {
+                self.reset = true;
                 self.current = None;
+                self.config = GpioConfig::default();
                 false
             }
         }
     }
 
-    pub fn current_signal(&self) -> Option<&str> {
+    pub fn current(&self) -> Option<&Signal> {
         match self.current {
-            Some(idx) => Some(&self.signals[idx]),
+            Some(idx) => self.signals.get(idx),
             None => None,
         }
     }
+
+    pub fn current_signal(&self) -> Option<&str> {
+        self.current().map(|s| s.name())
+    }
+
+    pub fn config(&self) -> &GpioConfig {
+        &self.config
+    }
+
+    // Configuration must be permitted by the selected signal, locked pin refuses it
+    pub fn set_config(&mut self, config: GpioConfig) -> Result<()> {
+        if self.locked {
+            return Err(ErrorKind::PinLocked.into());
+        }
+
+        config.validate(self.current().map(|s| s.kind()))?;
+        self.config = config;
+
+        Ok(())
+    }
+
+    // Alternate function numbers describe the silicon rather than the configuration,
+    // locked pins take them too
+    pub fn set_signal_af(&mut self, signal: &str, af: u8) -> bool {
+        match self.signals.iter_mut().find(|s| s.name() == signal) {
+            Some(s) => {
+                s.set_af(af);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn signal_af(&self, signal: &str) -> Option<u8> {
+        self.find_signal(signal).and_then(|s| s.af())
+    }
+
+    pub fn current_af(&self) -> Option<u8> {
+        self.current().and_then(|s| s.af())
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -178,6 +345,17 @@ impl Pin {
         }
     }
 
+    // Pin type as named in the MCU database
+    pub fn pin_type(&self) -> &str {
+        match *self {
+            Pin::NC { .. } => "NC",
+            Pin::IO { .. } => "I/O",
+            Pin::BOOT { .. } => "BOOT",
+            Pin::NRST { .. } => "Reset",
+            Pin::POWER { .. } => "Power",
+        }
+    }
+
     pub fn position(&self) -> &Position {
         match *self {
             Pin::NC { ref position, .. } => &position,
@@ -229,6 +407,9 @@ mod tests {
                 label: "".to_string(),
                 signals: vec![],
                 current: None,
+                locked: false,
+                required: None,
+                config: GpioConfig::default(),
             }),
         };
 
@@ -246,6 +427,9 @@ mod tests {
                 label: "".to_string(),
                 signals: vec![],
                 current: None,
+                locked: false,
+                required: None,
+                config: GpioConfig::default(),
             }),
         };
 
@@ -264,11 +448,14 @@ mod tests {
                 reset: true,
                 label: "".to_string(),
                 signals: vec![
-                    "Input".to_string(),
-                    "Output".to_string(),
-                    "EXTI".to_string(),
+                    Signal::new("Input"),
+                    Signal::new("Output"),
+                    Signal::new("EXTI"),
                 ],
                 current: Some(0),
+                locked: false,
+                required: None,
+                config: GpioConfig::default(),
             }),
         };
 
@@ -294,11 +481,14 @@ mod tests {
                 reset: true,
                 label: "".to_string(),
                 signals: vec![
-                    "Input".to_string(),
-                    "Output".to_string(),
-                    "EXTI".to_string(),
+                    Signal::new("Input"),
+                    Signal::new("Output"),
+                    Signal::new("EXTI"),
                 ],
                 current: Some(1),
+                locked: false,
+                required: None,
+                config: GpioConfig::default(),
             }),
         };
 
@@ -307,6 +497,11 @@ mod tests {
 
         assert_eq!(ret, true);
         assert_eq!(params.current_signal().unwrap(), "Output");
+        assert_eq!(params.is_reset(), false);
+
+        params.reset();
+        assert_eq!(params.is_reset(), true);
+        assert!(params.current_signal().is_none());
     }
 
     #[test]
@@ -318,11 +513,14 @@ mod tests {
                 reset: true,
                 label: "".to_string(),
                 signals: vec![
-                    "Input".to_string(),
-                    "Output".to_string(),
-                    "EXTI".to_string(),
+                    Signal::new("Input"),
+                    Signal::new("Output"),
+                    Signal::new("EXTI"),
                 ],
                 current: Some(0),
+                locked: false,
+                required: None,
+                config: GpioConfig::default(),
             }),
         };
 
@@ -333,6 +531,153 @@ mod tests {
         assert_eq!(params.current_signal().is_none(), true);
     }
 
+    #[test]
+    fn pin_signal_af() {
+        let mut pin = Pin::IO {
+            name: "PA9".to_string(),
+            position: Position::Linear(42),
+            params: Box::new(IOPin {
+                reset: true,
+                label: "".to_string(),
+                signals: vec![
+                    Signal::new("TIM1_CH2"),
+                    Signal::new("USART1_TX"),
+                    Signal::new("GPIO"),
+                ],
+                current: None,
+                locked: false,
+                required: None,
+                config: GpioConfig::default(),
+            }),
+        };
+
+        let params = pin.params_mut().unwrap();
+
+        assert_eq!(params.set_signal_af("USART1_TX", 7), true);
+        assert_eq!(params.set_signal_af("Missing", 7), false);
+        assert_eq!(params.signal_af("USART1_TX"), Some(7));
+        assert_eq!(params.signal_af("TIM1_CH2"), None);
+        assert_eq!(params.current_af(), None);
+
+        params.select_signal("USART1_TX");
+        assert_eq!(params.current_af(), Some(7));
+    }
+
+    #[test]
+    fn pin_locked() {
+        let mut pin = PinBuilder::new("I/O", Position::Linear(42), "PA9")
+            .signals(vec![String::from("USART1_TX"), String::from("GPIO")], 0)
+            .finish();
+
+        let params = pin.params_mut().unwrap();
+        params.lock();
+
+        assert_eq!(params.select_signal("GPIO"), false);
+        params.reset();
+        assert_eq!(params.current_signal(), Some("USART1_TX"));
+        assert_eq!(params.is_reset(), false);
+
+        params.unlock();
+        assert_eq!(params.select_signal("GPIO"), true);
+    }
+
+    #[test]
+    fn pin_reset_locked() {
+        use gpio::Pull;
+
+        let mut pin = PinBuilder::new("I/O", Position::Linear(42), "PA9")
+            .signals(vec![String::from("USART1_TX"), String::from("GPIO")], 1)
+            .finish();
+
+        let params = pin.params_mut().unwrap();
+        assert_eq!(params.is_reset(), false);
+        let mut config = *params.config();
+        config.pull = Pull::Up;
+        params.set_config(config).unwrap();
+
+        params.lock();
+        params.reset();
+        assert_eq!(params.is_reset(), false);
+        assert_eq!(params.current_signal(), Some("GPIO"));
+        assert_eq!(*params.config(), config);
+
+        params.unlock();
+        params.reset();
+        assert_eq!(params.is_reset(), true);
+        assert_eq!(params.current_signal(), None);
+        assert_eq!(*params.config(), GpioConfig::default());
+    }
+
+    #[test]
+    fn pin_required() {
+        let mut pin = PinBuilder::new("I/O", Position::Linear(42), "PA9")
+            .reset_signals(vec![String::from("USART1_TX"), String::from("GPIO")])
+            .finish();
+
+        let params = pin.params_mut().unwrap();
+
+        assert_eq!(params.require_signal("SPI1_SCK"), false);
+        assert_eq!(params.required_signal(), None);
+        assert_eq!(params.require_signal("USART1_TX"), true);
+        assert_eq!(params.required_signal(), Some("USART1_TX"));
+
+        params.clear_required();
+        assert_eq!(params.required_signal(), None);
+    }
+
+    #[test]
+    fn pin_config() {
+        use gpio::{Mode, Pull};
+
+        let mut pin = PinBuilder::new("I/O", Position::Linear(42), "PA9")
+            .reset_signals(vec![String::from("USART1_TX"), String::from("GPIO")])
+            .finish();
+
+        let params = pin.params_mut().unwrap();
+        let mut config = GpioConfig::default();
+        config.mode = Mode::Input;
+        assert!(params.set_config(config).is_err());
+
+        params.select_signal("USART1_TX");
+        assert_eq!(params.config().mode, Mode::AlternateFunction);
+        config.pull = Pull::Up;
+        assert!(params.set_config(config).is_err());
+
+        params.select_signal("GPIO");
+        params.set_config(config).unwrap();
+        assert_eq!(*params.config(), config);
+
+        params.lock();
+        assert!(params.set_config(GpioConfig::default()).is_err());
+        assert_eq!(params.set_label("TX"), false);
+        assert_eq!(params.label(), "");
+        assert_eq!(*params.config(), config);
+        params.unlock();
+
+        params.reset();
+        assert_eq!(*params.config(), GpioConfig::default());
+    }
+
+    #[test]
+    fn position_display() {
+        assert_eq!(Position::Linear(12).to_string(), "12");
+        assert_eq!(Position::Grid(1, 1).to_string(), "A1");
+        assert_eq!(Position::Grid(5, 7).to_string(), "E7");
+        assert_eq!(Position::Grid(8, 2).to_string(), "H2");
+        assert_eq!(Position::Grid(9, 2).to_string(), "J2");
+        assert_eq!(Position::Grid(21, 3).to_string(), "AA3");
+    }
+
+    #[test]
+    fn position_parse() {
+        assert_eq!("12".parse::<Position>().unwrap(), Position::Linear(12));
+        assert_eq!("F7".parse::<Position>().unwrap(), Position::Grid(6, 7));
+        assert_eq!("AA3".parse::<Position>().unwrap(), Position::Grid(21, 3));
+        assert!("I3".parse::<Position>().is_err());
+        assert!("F".parse::<Position>().is_err());
+        assert!("7F".parse::<Position>().is_err());
+    }
+
     // TODO: Create unknown Pin type and fail
     #[test]
     fn build_nc_pin() {
@@ -390,4 +735,15 @@ mod tests {
             _ => assert!(false),
         }
     }
+
+    #[test]
+    fn build_io_pin_reset() {
+        let pinbuilder = PinBuilder::new("I/O", Position::Linear(10), "PA1")
+            .reset_signals(vec![String::from("Input"), String::from("Output")]);
+        let pin = pinbuilder.finish();
+
+        let params = pin.params().unwrap();
+        assert_eq!(params.signals().len(), 2);
+        assert!(params.current_signal().is_none());
+    }
 }
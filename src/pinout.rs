@@ -0,0 +1,566 @@
+use std::fmt::Write;
+
+use errors::*;
+use mcu::MCUConf;
+use pin::Pin;
+
+// Distances of the KiCad symbol in 0.01 mm
+const PIN_PITCH: i32 = 254;
+const PIN_LENGTH: i32 = 254;
+const BODY_HALF_WIDTH: i32 = 1524;
+
+// Pinout class
+//
+//    Exports the pin table of the MCU for board design, as CSV and as KiCad symbol
+//    library. Pins carry the user label when set so the schematic matches the firmware.
+//
+//    \sa Pins, IOPin
+#[derive(Debug)]
+pub struct Pinout<'a> {
+    name: &'a str,
+    pins: &'a Vec<Pin>,
+}
+
+impl<'a> Pinout<'a> {
+    pub fn new(mcu_conf: &'a MCUConf) -> Pinout<'a> {
+        Pinout {
+            name: mcu_conf.get_name(),
+            pins: mcu_conf.get_pins().pins(),
+        }
+    }
+
+    // Pins in package order
+    fn sorted_pins(&self) -> Vec<&'a Pin> {
+        let mut pins: Vec<&Pin> = self.pins.iter().collect();
+        pins.sort_by_key(|pin| *pin.position());
+        pins
+    }
+
+    // One row per pin: position, pin name, type, selected signal and user label
+    pub fn csv(&self) -> String {
+        let mut out = String::new();
+
+        writeln!(out, "Position,Name,Type,Signal,Label").unwrap();
+
+        for pin in self.sorted_pins() {
+            let (signal, label) = match pin.params() {
+                Some(params) => (params.current_signal().unwrap_or(""), params.label()),
+                None => ("", ""),
+            };
+
+            writeln!(
+                out,
+                "{},{},{},{},{}",
+                pin.position(),
+                csv_field(pin.name()),
+                pin.pin_type(),
+                csv_field(signal),
+                csv_field(label)
+            )
+            .unwrap();
+        }
+
+        out
+    }
+
+    // KiCad 6 symbol library with one symbol, first half of the pins on the left side
+    // and the rest on the right side from the top
+    pub fn kicad_symbol(&self) -> String {
+        let mut out = String::new();
+        let pins = self.sorted_pins();
+
+        let left = (pins.len() + 1) / 2;
+        let half_height = (left as i32 + 1) * PIN_PITCH / 2;
+
+        writeln!(
+            out,
+            "(kicad_symbol_lib (version 20211014) (generator scrapyard)"
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "  (symbol \"{}\" (in_bom yes) (on_board yes)",
+            kicad_string(self.name)
+        )
+        .unwrap();
+        write_property(&mut out, 0, "Reference", "U", half_height + PIN_PITCH);
+        write_property(&mut out, 1, "Value", self.name, -half_height - PIN_PITCH);
+        // Footprint is left to the board designer, the package name isn't a library reference
+        write_property(&mut out, 2, "Footprint", "", -half_height - 2 * PIN_PITCH);
+
+        writeln!(out, "    (symbol \"{}_0_1\"", kicad_string(self.name)).unwrap();
+        writeln!(
+            out,
+            "      (rectangle (start {} {}) (end {} {})",
+            mm(-BODY_HALF_WIDTH),
+            mm(half_height),
+            mm(BODY_HALF_WIDTH),
+            mm(-half_height)
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "        (stroke (width 0.254) (type default) (color 0 0 0 0)) (fill (type background)))"
+        )
+        .unwrap();
+        writeln!(out, "    )").unwrap();
+
+        writeln!(out, "    (symbol \"{}_1_1\"", kicad_string(self.name)).unwrap();
+
+        for (idx, pin) in pins.iter().enumerate() {
+            let (x, row, angle) = if idx < left {
+                (-BODY_HALF_WIDTH - PIN_LENGTH, idx, 0)
+            } else {
+                (BODY_HALF_WIDTH + PIN_LENGTH, idx - left, 180)
+            };
+            let y = half_height - (row as i32 + 1) * PIN_PITCH;
+
+            let name = match pin.params() {
+                Some(params) if !params.label().is_empty() => params.label(),
+                _ => pin.name(),
+            };
+
+            writeln!(
+                out,
+                "      (pin {} line (at {} {} {}) (length {})",
+                electrical_type(pin),
+                mm(x),
+                mm(y),
+                angle,
+                mm(PIN_LENGTH)
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "        (name \"{}\" (effects (font (size 1.27 1.27))))",
+                kicad_string(name)
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "        (number \"{}\" (effects (font (size 1.27 1.27)))))",
+                pin.position()
+            )
+            .unwrap();
+        }
+
+        writeln!(out, "    )").unwrap();
+        writeln!(out, "  )").unwrap();
+        writeln!(out, ")").unwrap();
+
+        out
+    }
+}
+
+// Net connected to package pin, position as printed on the package such as "12" or "F7"
+#[derive(Debug, PartialEq)]
+pub struct Net {
+    pub position: String,
+    pub name: String,
+}
+
+// Reads nets from CSV with header row, position comes from "Position", "Pin" or "Number"
+// column and net from "Net" or "Label" column. Rows with no net are skipped.
+pub fn read_csv(text: &str) -> Result<Vec<Net>> {
+    let mut records = csv_records(text)?.into_iter();
+
+    let header = match records.next() {
+        Some(header) => header,
+        None => return Err(ErrorKind::InvalidNetlist(String::from("empty CSV")).into()),
+    };
+
+    let column = |names: &[&str]| {
+        header
+            .iter()
+            .position(|h| names.iter().any(|n| h.trim().eq_ignore_ascii_case(n)))
+    };
+
+    let (position, net) = match (
+        column(&["Position", "Pin", "Number"]),
+        column(&["Net", "Label"]),
+    ) {
+        (Some(position), Some(net)) => (position, net),
+        _ => {
+            return Err(ErrorKind::InvalidNetlist(String::from(
+                "CSV has no pin position or net column",
+            ))
+            .into())
+        }
+    };
+
+    let nets = records
+        .filter_map(|record| match (record.get(position), record.get(net)) {
+            (Some(position), Some(net)) if !net.trim().is_empty() => Some(Net {
+                position: position.trim().to_string(),
+                name: net.trim().to_string(),
+            }),
+            _ => None,
+        })
+        .collect();
+
+    Ok(nets)
+}
+
+// Reads nets connected to the component such as "U1" from KiCad netlist, nets named by
+// KiCad such as "Net-(U1-Pad3)" are skipped and hierarchical path is dropped
+pub fn read_kicad_netlist(text: &str, reference: &str) -> Result<Vec<Net>> {
+    let root = Sexp::parse(text)?;
+
+    let nets = match root.find("nets") {
+        Some(nets) => nets,
+        None => return Err(ErrorKind::InvalidNetlist(String::from("no nets")).into()),
+    };
+
+    let mut found = vec![];
+
+    for net in nets.children("net") {
+        let name = match net.find("name").and_then(|n| n.value()) {
+            Some(name) => name.rsplit('/').next().unwrap_or(name),
+            None => continue,
+        };
+
+        if name.is_empty() || name.starts_with("Net-(") || name.starts_with("unconnected-(") {
+            continue;
+        }
+
+        for node in net.children("node") {
+            let on_component = node.find("ref").and_then(|r| r.value()) == Some(reference);
+
+            if let (true, Some(pin)) = (on_component, node.find("pin").and_then(|p| p.value())) {
+                found.push(Net {
+                    position: pin.to_string(),
+                    name: name.to_string(),
+                });
+            }
+        }
+    }
+
+    Ok(found)
+}
+
+// Records of CSV text, quoted fields may hold separators, quotes and new lines
+fn csv_records(text: &str) -> Result<Vec<Vec<String>>> {
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            (true, c) => field.push(c),
+            (false, '"') => quoted = true,
+            (false, ',') => record.push(field.split_off(0)),
+            (false, '\r') => (),
+            (false, '\n') => {
+                record.push(field.split_off(0));
+                records.push(record.split_off(0));
+            }
+            (false, c) => field.push(c),
+        }
+    }
+
+    if quoted {
+        return Err(ErrorKind::InvalidNetlist(String::from("unterminated quote")).into());
+    }
+
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+// S-expression of KiCad files
+#[derive(Debug, PartialEq)]
+enum Sexp {
+    Atom(String),
+    List(Vec<Sexp>),
+}
+
+impl Sexp {
+    fn parse(text: &str) -> Result<Sexp> {
+        let invalid = |reason: &str| Error::from(ErrorKind::InvalidNetlist(reason.to_string()));
+
+        let mut stack: Vec<Vec<Sexp>> = vec![];
+        let mut root = None;
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            let item = match c {
+                '(' => {
+                    stack.push(vec![]);
+                    continue;
+                }
+                ')' => Sexp::List(stack.pop().ok_or_else(|| invalid("unbalanced ')'"))?),
+                '"' => {
+                    let mut atom = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some('\\') => atom.extend(chars.next()),
+                            Some(c) => atom.push(c),
+                            None => return Err(invalid("unterminated string")),
+                        }
+                    }
+                    Sexp::Atom(atom)
+                }
+                c if c.is_whitespace() => continue,
+                c => {
+                    let mut atom = c.to_string();
+                    while let Some(&c) = chars.peek() {
+                        if c == '(' || c == ')' || c.is_whitespace() {
+                            break;
+                        }
+                        atom.push(c);
+                        chars.next();
+                    }
+                    Sexp::Atom(atom)
+                }
+            };
+
+            match stack.last_mut() {
+                Some(list) => list.push(item),
+                None if root.is_none() => root = Some(item),
+                None => return Err(invalid("more than one top level expression")),
+            }
+        }
+
+        if !stack.is_empty() {
+            return Err(invalid("unbalanced '('"));
+        }
+
+        root.ok_or_else(|| invalid("empty netlist"))
+    }
+
+    // Keyword of list such as "net" in (net (code 1) ...)
+    fn keyword(&self) -> Option<&str> {
+        match *self {
+            Sexp::List(ref items) => match items.first() {
+                Some(Sexp::Atom(keyword)) => Some(keyword),
+                _ => None,
+            },
+            Sexp::Atom(_) => None,
+        }
+    }
+
+    fn children(&self, keyword: &str) -> Vec<&Sexp> {
+        match *self {
+            Sexp::List(ref items) => items
+                .iter()
+                .filter(|item| item.keyword() == Some(keyword))
+                .collect(),
+            Sexp::Atom(_) => vec![],
+        }
+    }
+
+    fn find(&self, keyword: &str) -> Option<&Sexp> {
+        self.children(keyword).into_iter().next()
+    }
+
+    // Value of list such as "12" in (pin "12")
+    fn value(&self) -> Option<&str> {
+        match *self {
+            Sexp::List(ref items) => match items.get(1) {
+                Some(Sexp::Atom(value)) => Some(value),
+                _ => None,
+            },
+            Sexp::Atom(_) => None,
+        }
+    }
+}
+
+fn write_property(out: &mut String, id: u8, key: &str, value: &str, y: i32) {
+    writeln!(
+        out,
+        "    (property \"{}\" \"{}\" (id {}) (at 0 {} 0)",
+        key,
+        kicad_string(value),
+        id,
+        mm(y)
+    )
+    .unwrap();
+    writeln!(out, "      (effects (font (size 1.27 1.27))))").unwrap();
+}
+
+fn electrical_type(pin: &Pin) -> &str {
+    match *pin {
+        Pin::NC { .. } => "no_connect",
+        Pin::IO { .. } => "bidirectional",
+        Pin::BOOT { .. } | Pin::NRST { .. } => "input",
+        Pin::POWER { .. } => "power_in",
+    }
+}
+
+// Millimeters from 0.01 mm, "-17.78" or "2.54"
+fn mm(value: i32) -> String {
+    let sign = if value < 0 { "-" } else { "" };
+    let value = value.abs();
+
+    match value % 100 {
+        0 => format!("{}{}", sign, value / 100),
+        cents => format!("{}{}.{:02}", sign, value / 100, cents),
+    }
+}
+
+fn kicad_string(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// Quotes fields with separators or quotes
+fn csv_field(value: &str) -> String {
+    if value.contains(&[',', '"', '\n'][..]) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use mcu::MCU;
+    use std::path::Path;
+
+    fn mcu_conf() -> MCUConf {
+        let sample = Path::new("./samples/STM32F030C6Tx.json");
+        let mut mcu_conf = MCU::new(sample).unwrap().finish();
+
+        {
+            let pins = mcu_conf.get_pins_mut();
+            let idx = pins.find_pin("PA9")[0];
+            let params = pins.pins[idx].params_mut().unwrap();
+            params.select_signal("USART1_TX");
+            params.set_label("DEBUG_TX, 3V3");
+        }
+
+        mcu_conf
+    }
+
+    #[test]
+    fn csv() {
+        let mcu_conf = mcu_conf();
+        let csv = Pinout::new(&mcu_conf).csv();
+        let mut lines = csv.lines();
+
+        assert_eq!(lines.next(), Some("Position,Name,Type,Signal,Label"));
+
+        let pa9 = csv.lines().find(|l| l.contains(",PA9,")).unwrap();
+        assert!(pa9.ends_with(",PA9,I/O,USART1_TX,\"DEBUG_TX, 3V3\""));
+        assert_eq!(csv.lines().count(), mcu_conf.get_pins().pins().len() + 1);
+    }
+
+    #[test]
+    fn kicad_symbol() {
+        let mcu_conf = mcu_conf();
+        let symbol = Pinout::new(&mcu_conf).kicad_symbol();
+
+        assert!(symbol.starts_with("(kicad_symbol_lib (version 20211014)"));
+        assert!(symbol.contains("  (symbol \"STM32F030C6Tx\" (in_bom yes) (on_board yes)\n"));
+        assert!(symbol.contains("    (property \"Footprint\" \"\" (id 2)"));
+        assert!(symbol.contains("        (name \"DEBUG_TX, 3V3\" (effects"));
+        assert!(symbol.contains("      (pin bidirectional line (at -17.78 "));
+        assert_eq!(
+            symbol.matches("(pin ").count(),
+            mcu_conf.get_pins().pins().len()
+        );
+    }
+
+    #[test]
+    fn import_csv() {
+        let mut mcu_conf = mcu_conf();
+        let exported = Pinout::new(&mcu_conf).csv();
+
+        let nets = read_csv(&exported).unwrap();
+        let pa9 = Net {
+            position: "30".to_string(),
+            name: "DEBUG_TX, 3V3".to_string(),
+        };
+        assert_eq!(nets, vec![pa9]);
+
+        let nets = read_csv("Pin,Net\r\n12,USART1_TX\r\n13,LED\r\n14,\r\n").unwrap();
+        assert_eq!(nets.len(), 2);
+
+        let pins = mcu_conf.get_pins_mut();
+        assert_eq!(pins.apply_nets(&nets), 2);
+
+        let pa2 = pins.pins[pins.find_pin("PA2")[0]].params().unwrap();
+        assert_eq!(pa2.label(), "USART1_TX");
+        assert_eq!(pa2.current_signal(), Some("USART1_TX"));
+
+        let pa3 = pins.pins[pins.find_pin("PA3")[0]].params().unwrap();
+        assert_eq!(pa3.label(), "LED");
+        assert!(pa3.is_reset());
+
+        assert!(read_csv("Name,Type\nPA1,I/O\n").is_err());
+        assert!(read_csv("Pin,Net\n12,\"open\n").is_err());
+    }
+
+    #[test]
+    fn import_kicad_netlist() {
+        let netlist = r#"(export (version "E")
+  (nets
+    (net (code "1") (name "/mcu/USART1_TX")
+      (node (ref "U1") (pin "12") (pintype "bidirectional"))
+      (node (ref "J1") (pin "2") (pintype "passive")))
+    (net (code "2") (name "Net-(U1-Pad13)")
+      (node (ref "U1") (pin "13")))
+    (net (code 3) (name LED)
+      (node (ref U1) (pin 14))
+      (node (ref D1) (pin 1)))))"#;
+
+        let nets = read_kicad_netlist(netlist, "U1").unwrap();
+        assert_eq!(
+            nets,
+            vec![
+                Net {
+                    position: "12".to_string(),
+                    name: "USART1_TX".to_string(),
+                },
+                Net {
+                    position: "14".to_string(),
+                    name: "LED".to_string(),
+                },
+            ]
+        );
+
+        let mut mcu_conf = mcu_conf();
+        let pins = mcu_conf.get_pins_mut();
+        let pa2 = pins.find_pin("PA2")[0];
+        pins.pins[pa2].params_mut().unwrap().lock();
+
+        // Locked PA2 keeps its label and reset state
+        assert_eq!(pins.apply_nets(&nets), 1);
+        let params = pins.pins[pa2].params().unwrap();
+        assert_eq!(params.label(), "");
+        assert!(params.is_reset());
+
+        pins.pins[pa2].params_mut().unwrap().unlock();
+        assert_eq!(pins.apply_nets(&nets), 2);
+        let params = pins.pins[pa2].params().unwrap();
+        assert_eq!(params.label(), "USART1_TX");
+        assert_eq!(params.current_signal(), Some("USART1_TX"));
+
+        assert!(read_kicad_netlist("(export (nets)", "U1").is_err());
+        assert!(read_kicad_netlist("(export)", "U1").is_err());
+    }
+
+    #[test]
+    fn units() {
+        assert_eq!(mm(-1778), "-17.78");
+        assert_eq!(mm(254), "2.54");
+        assert_eq!(mm(1270), "12.70");
+        assert_eq!(mm(-500), "-5");
+        assert_eq!(csv_field("a\"b"), "\"a\"\"b\"");
+    }
+}
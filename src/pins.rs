@@ -1,4 +1,8 @@
+use conflicts::{self, ConflictReport};
+use errors::*;
+use gpio::port_name;
 use pin::Pin;
+use pinout::Net;
 
 // Pins class
 //
@@ -27,12 +31,10 @@ impl Pins {
                 pins.push(idx);
             } else {
                 match pin.params() {
-                    Some(params) => {
-                        match params.signals().iter().position(|ref s| s.contains(name)) {
-                            Some(_) => pins.push(idx),
-                            None => (),
-                        }
-                    }
+                    Some(params) => match params.find_signal(name) {
+                        Some(_) => pins.push(idx),
+                        None => (),
+                    },
                     None => (),
                 }
             }
@@ -46,7 +48,7 @@ impl Pins {
 
         for (idx, pin) in self.pins.iter().enumerate() {
             match pin.params() {
-                Some(params) => match params.signals().iter().position(|ref s| s.contains(name)) {
+                Some(params) => match params.find_signal(name) {
                     Some(_) => if idx != pin_idx {
                         pins.push(idx)
                     },
@@ -59,76 +61,245 @@ impl Pins {
         pins
     }
 
-    // Configures or resets pins belonging to peripheral
+    // Finds pins having any signal of peripheral instance such as "USART1"
+    pub fn find_instance(&self, instance: &str) -> Vec<usize> {
+        let mut pins: Vec<usize> = vec![];
 
-    //    Handles the configuration of pin with signals \p peripheralPins. Iterates through pins until finds pin with corresponding peripheral signal. Parameter \p state either sets the alternate signal on the pin if it's reset or resets the pin if it's configured with the same alternate signal.
+        for (idx, pin) in self.pins.iter().enumerate() {
+            if let Some(params) = pin.params() {
+                if params.signals().iter().any(|s| s.instance() == instance) {
+                    pins.push(idx);
+                }
+            }
+        }
 
-    //    \param peripheralPins List of alternate functions to configure
-    //    \param state Configure or reset the pin
-    //
-    //fn peripheral_pins() {}
-    //void Pins::onPeripheralPins(QList<QString> peripheralPins, Pin::PinState state)
-    //{
+        pins
+    }
 
-    //    Pin::AlternateFunction alternateFunction;
+    // Checks for pins assigned twice, signals selected on more pins and half-routed
+    // peripherals
+    pub fn check_conflicts(&self) -> ConflictReport {
+        conflicts::check(self)
+    }
 
-    //    bool configured;
+    // Fails with the report when there are any conflicts
+    pub fn validate(&self) -> Result<()> {
+        let report = self.check_conflicts();
 
-    //    // TODO: Handle when the are no pins available for configuration
-    //    foreach (QString value, peripheralPins) {
+        if report.is_ok() {
+            Ok(())
+        } else {
+            Err(ErrorKind::PinConflicts(report.to_string()).into())
+        }
+    }
 
-    //        configured = false;
+    // Configures pins for the peripheral signals such as ["SPI1_SCK", "SPI1_MOSI"]
+    //
+    //    Signals already selected on some pin are kept, the others get pins in reset
+    //    state. Signal required on a pin by the user goes only there. When a signal runs
+    //    out of pins, earlier choices are revisited using the alternate pins. Locked pins
+    //    are never touched. Nothing is changed when the signals can't be placed.
+    //
+    //    Returns indices of pins in the order of signals.
+    pub fn assign_peripheral_pins(&mut self, signals: &[&str]) -> Result<Vec<usize>> {
+        let mut placed: Vec<Option<usize>> = vec![None; signals.len()];
+        let mut pending: Vec<(usize, Vec<usize>)> = vec![];
+
+        for (idx, signal) in signals.iter().enumerate() {
+            let selected = self
+                .pins
+                .iter()
+                .position(|pin| pin.params().and_then(|p| p.current_signal()) == Some(*signal));
+
+            match selected {
+                Some(pin_idx) => placed[idx] = Some(pin_idx),
+                None => {
+                    let required: Vec<usize> = self
+                        .candidate_pins(signal)
+                        .into_iter()
+                        .filter(|&pin_idx| {
+                            self.pins[pin_idx]
+                                .params()
+                                .and_then(|p| p.required_signal())
+                                == Some(*signal)
+                        })
+                        .collect();
+
+                    let candidates: Vec<usize> = if required.is_empty() {
+                        self.candidate_pins(signal)
+                    } else {
+                        required
+                    };
+
+                    // Pins required for another signal are kept for it
+                    let candidates: Vec<usize> = candidates
+                        .into_iter()
+                        .filter(|&pin_idx| match self.pins[pin_idx].params() {
+                            Some(params) => {
+                                params.is_reset()
+                                    && !params.is_locked()
+                                    && params.required_signal().map_or(true, |r| r == *signal)
+                            }
+                            None => false,
+                        })
+                        .collect();
+
+                    if candidates.is_empty() {
+                        return Err(ErrorKind::NoPinAvailable(signal.to_string()).into());
+                    }
 
-    //        foreach (pin, pins) {
+                    pending.push((idx, candidates));
+                }
+            }
+        }
 
-    //            foreach (alternateFunction, pin->getAlternateFunctions()) {
+        // Most constrained signals go first
+        pending.sort_by_key(|p| p.1.len());
+
+        // Configured pins, kept signals included, take their port pin from aliased pins
+        let mut used: Vec<usize> = self
+            .pins
+            .iter()
+            .enumerate()
+            .filter(|&(_, pin)| match pin.params() {
+                Some(params) => !params.is_reset(),
+                None => false,
+            })
+            .map(|(idx, _)| idx)
+            .collect();
+
+        if !self.solve(&pending, &mut used, &mut placed) {
+            // Shortest run of pending signals without solution ends with the one that
+            // doesn't fit
+            let failed = (1..pending.len())
+                .find(|&n| !self.solve(&pending[..n], &mut used.clone(), &mut placed.clone()))
+                .unwrap_or(pending.len());
+            let signal = signals[pending[failed - 1].0];
+            return Err(ErrorKind::NoPinAvailable(signal.to_string()).into());
+        }
 
-    //                if (alternateFunction.name == value) {
+        for &(idx, _) in &pending {
+            if let Some(pin_idx) = placed[idx] {
+                if let Some(params) = self.pins[pin_idx].params_mut() {
+                    params.select_signal(signals[idx]);
+                }
+            }
+        }
 
-    //                    if (state == Pin::PIN_ASSIGNED) {
+        Ok(placed.into_iter().flatten().collect())
+    }
 
-    //                        if (pin->isReset()) {
+    // Resets pins configured with the signals except locked ones, returns number of
+    // reset pins
+    pub fn reset_peripheral_pins(&mut self, signals: &[&str]) -> usize {
+        let mut count = 0;
+
+        for pin in &mut self.pins {
+            if let Some(params) = pin.params_mut() {
+                let configured = match params.current_signal() {
+                    Some(current) => signals.contains(&current),
+                    None => false,
+                };
+
+                if configured && !params.is_locked() {
+                    params.reset();
+                    count += 1;
+                }
+            }
+        }
 
-    //                            //pin->setGpioMode(GPIOStr::gpioModeStr[3]);
-    //                            pin->selectAlternateFunction(value);
+        count
+    }
 
-    //                            //emit pinFunctionChanged (pin->getName(), state, value);
+    // Labels pins with names of the nets connected to them, net named after a signal of
+    // the pin such as "USART1_TX" also selects the signal. Locked pins are left alone.
+    // Returns number of labelled pins.
+    pub fn apply_nets(&mut self, nets: &[Net]) -> usize {
+        let mut count = 0;
 
-    //                            configured = true;
+        for net in nets {
+            for pin in &mut self.pins {
+                if !pin.position().to_string().eq_ignore_ascii_case(&net.position) {
+                    continue;
+                }
 
-    //                            break;
-    //                            }
-    //                        }
-    //                    else {
+                if let Some(params) = pin.params_mut() {
+                    if !params.set_label(&net.name) {
+                        continue;
+                    }
 
-    //                        if (pin->getAlternateFunction().name == value) {
+                    if params.find_signal(&net.name).is_some() {
+                        params.select_signal(&net.name);
+                    }
 
-    //                            pin->resetPin();
+                    count += 1;
+                }
+            }
+        }
 
-    //                            //emit pinFunctionChanged (pin->getName(), Pin::Pins_RESET, "");
+        count
+    }
 
-    //                            configured = true;
+    // Pins providing the signal, first one followed by its alternates
+    fn candidate_pins(&self, signal: &str) -> Vec<usize> {
+        let first = self.pins.iter().position(|pin| match pin.params() {
+            Some(params) => params.find_signal(signal).is_some(),
+            None => false,
+        });
+
+        match first {
+            Some(first) => {
+                let mut pins = vec![first];
+                pins.extend(self.find_alternate_pins(first, signal));
+                pins
+            }
+            None => vec![],
+        }
+    }
 
-    //                            break;
-    //                            }
-    //                        }
-    //                    }
-    //                }
+    // Backtracking over pending signals, pins bonded to the same port pin exclude each
+    // other
+    fn solve(
+        &self,
+        pending: &[(usize, Vec<usize>)],
+        used: &mut Vec<usize>,
+        placed: &mut Vec<Option<usize>>,
+    ) -> bool {
+        let (idx, candidates) = match pending.first() {
+            Some(&(idx, ref candidates)) => (idx, candidates),
+            None => return true,
+        };
+
+        for &pin_idx in candidates {
+            let port = port_name(self.pins[pin_idx].name());
+
+            if used.iter().any(|&u| port_name(self.pins[u].name()) == port) {
+                continue;
+            }
 
-    //            if (configured){
+            used.push(pin_idx);
+            placed[idx] = Some(pin_idx);
 
-    //                break;
-    //                }
-    //            }
-    //        }
-    //}
+            if self.solve(&pending[1..], used, placed) {
+                return true;
+            }
+
+            used.pop();
+            placed[idx] = None;
+        }
+
+        false
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
+    use super::*;
+    use mcu::{MCUConf, MCU};
+    use pin::{PinBuilder, Position};
+    use serde_json;
     use std::path::Path;
-    use mcu::MCU;
 
     #[test]
     fn load_pins_ok() {
@@ -165,6 +336,32 @@ mod tests {
         assert_eq!(vec![10, 32], found);
     }
 
+    #[test]
+    fn find_signal_exact() {
+        let sample = Path::new("./samples/STM32F030C6Tx.json");
+        let mcu = MCU::new(sample).unwrap();
+
+        let mcu_conf = mcu.finish();
+        let pins = mcu_conf.get_pins();
+
+        let found = pins.find_pin("TIM1_CH1");
+
+        assert_eq!(vec![28], found);
+    }
+
+    #[test]
+    fn find_instance_ok() {
+        let sample = Path::new("./samples/STM32F030C6Tx.json");
+        let mcu = MCU::new(sample).unwrap();
+
+        let mcu_conf = mcu.finish();
+        let pins = mcu_conf.get_pins();
+
+        let found = pins.find_instance("TIM3");
+
+        assert_eq!(vec![15, 16, 17, 18, 39, 40], found);
+    }
+
     #[test]
     fn find_signal_empty() {
         let sample = Path::new("./samples/STM32F030C6Tx.json");
@@ -204,4 +401,190 @@ mod tests {
 
         assert_eq!(Vec::<usize>::new(), found);
     }
+
+    fn pin_names(pins: &Pins, found: &[usize]) -> Vec<String> {
+        found
+            .iter()
+            .map(|&idx| pins.pins[idx].name().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn assign_peripheral_pins_ok() {
+        let sample = Path::new("./samples/STM32F030C6Tx.json");
+        let mut mcu_conf = MCU::new(sample).unwrap().finish();
+        let pins = mcu_conf.get_pins_mut();
+
+        let spi = pins
+            .assign_peripheral_pins(&["SPI1_SCK", "SPI1_MISO", "SPI1_MOSI"])
+            .unwrap();
+        assert_eq!(pin_names(pins, &spi), vec!["PA5", "PA6", "PA7"]);
+
+        let tim = pins
+            .assign_peripheral_pins(&["TIM3_CH1", "TIM3_CH2", "TIM3_CH3", "TIM3_CH4"])
+            .unwrap();
+        assert_eq!(pin_names(pins, &tim), vec!["PB4", "PB5", "PB0", "PB1"]);
+
+        // Already configured signals are kept
+        let again = pins
+            .assign_peripheral_pins(&["SPI1_SCK", "SPI1_MISO", "SPI1_MOSI"])
+            .unwrap();
+        assert_eq!(again, spi);
+        assert!(pins.check_conflicts().is_ok());
+    }
+
+    fn lock(pins: &mut Pins, name: &str) {
+        let idx = pins.find_pin(name)[0];
+        pins.pins[idx].params_mut().unwrap().lock();
+    }
+
+    #[test]
+    fn assign_peripheral_pins_locked() {
+        let sample = Path::new("./samples/STM32F030C6Tx.json");
+        let mut mcu_conf = MCU::new(sample).unwrap().finish();
+        let pins = mcu_conf.get_pins_mut();
+
+        lock(pins, "PA6");
+        let spi = pins
+            .assign_peripheral_pins(&["SPI1_SCK", "SPI1_MISO", "SPI1_MOSI"])
+            .unwrap();
+        assert_eq!(pin_names(pins, &spi), vec!["PA5", "PB14", "PA7"]);
+
+        // Both pins of TIM3_CH1 are locked, nothing gets configured
+        lock(pins, "PB4");
+        assert!(pins
+            .assign_peripheral_pins(&["TIM3_CH2", "TIM3_CH1"])
+            .is_err());
+        assert!(pins.find_pin("TIM3_CH2").iter().all(|&idx| pins.pins[idx]
+            .params()
+            .unwrap()
+            .current_signal()
+            != Some("TIM3_CH2")));
+
+        let pb4 = pins.find_pin("PB4")[0];
+        pins.pins[pb4].params_mut().unwrap().unlock();
+        let tim = pins
+            .assign_peripheral_pins(&["TIM3_CH2", "TIM3_CH1"])
+            .unwrap();
+        assert_eq!(pin_names(pins, &tim), vec!["PB5", "PB4"]);
+
+        lock(pins, "PB4");
+        assert_eq!(pins.reset_peripheral_pins(&["SPI1_MISO", "TIM3_CH1"]), 1);
+        assert!(pins.pins[pins.find_pin("PB14")[0]]
+            .params()
+            .unwrap()
+            .is_reset());
+        assert_eq!(
+            pins.pins[pb4].params().unwrap().current_signal(),
+            Some("TIM3_CH1")
+        );
+    }
+
+    #[test]
+    fn assign_peripheral_pins_required() {
+        let sample = Path::new("./samples/STM32F030C6Tx.json");
+        let mut mcu_conf = MCU::new(sample).unwrap().finish();
+        let pins = mcu_conf.get_pins_mut();
+
+        let pb3 = pins.find_pin("PB3")[0];
+        assert!(pins.pins[pb3]
+            .params_mut()
+            .unwrap()
+            .require_signal("SPI1_SCK"));
+
+        let spi = pins
+            .assign_peripheral_pins(&["SPI1_SCK", "SPI1_MOSI"])
+            .unwrap();
+        assert_eq!(pin_names(pins, &spi), vec!["PB3", "PA7"]);
+
+        // TIM1_CH2 has only PA9 which is kept for USART1_TX
+        let pa9 = pins.find_pin("PA9")[0];
+        assert!(pins.pins[pa9]
+            .params_mut()
+            .unwrap()
+            .require_signal("USART1_TX"));
+
+        assert!(pins.assign_peripheral_pins(&["TIM1_CH2"]).is_err());
+        assert!(pins.pins[pa9].params().unwrap().is_reset());
+
+        let usart = pins.assign_peripheral_pins(&["USART1_TX"]).unwrap();
+        assert_eq!(usart, vec![pa9]);
+    }
+
+    #[test]
+    fn locks_serialize() {
+        let sample = Path::new("./samples/STM32F030C6Tx.json");
+        let mut mcu_conf = MCU::new(sample).unwrap().finish();
+
+        {
+            let pins = mcu_conf.get_pins_mut();
+            pins.assign_peripheral_pins(&["USART1_TX"]).unwrap();
+            lock(pins, "PA2");
+        }
+
+        let json = serde_json::to_string(&mcu_conf).unwrap();
+        let mcu_conf: MCUConf = serde_json::from_str(&json).unwrap();
+        let pins = mcu_conf.get_pins();
+        let params = pins.pins[pins.find_pin("PA2")[0]].params().unwrap();
+
+        assert!(params.is_locked());
+        assert_eq!(params.current_signal(), Some("USART1_TX"));
+    }
+
+    fn io(position: u16, name: &str, signals: &[&str]) -> Pin {
+        PinBuilder::new("I/O", Position::Linear(position), name)
+            .reset_signals(signals.iter().map(|s| s.to_string()).collect())
+            .finish()
+    }
+
+    #[test]
+    fn assign_peripheral_pins_backtrack() {
+        let mut pins = Pins {
+            pins: vec![
+                io(1, "PA1", &["TIM3_CH1", "SPI1_MISO", "USART1_TX"]),
+                io(2, "PA2", &["TIM3_CH1"]),
+                io(3, "PA3", &["SPI1_MISO", "USART1_TX"]),
+            ],
+        };
+
+        // TIM3_CH1 placed on PA1 leaves USART1_TX without pin
+        let found = pins
+            .assign_peripheral_pins(&["TIM3_CH1", "SPI1_MISO", "USART1_TX"])
+            .unwrap();
+        assert_eq!(pin_names(&pins, &found), vec!["PA2", "PA1", "PA3"]);
+    }
+
+    #[test]
+    fn assign_peripheral_pins_unplaceable() {
+        let mut pins = Pins {
+            pins: vec![
+                io(1, "PA1", &["TIM2_CH1", "TIM2_CH2"]),
+                io(2, "PA2", &["USART2_TX"]),
+                io(3, "PA3", &["USART2_TX"]),
+                io(4, "PA4", &["USART2_TX"]),
+            ],
+        };
+
+        // TIM2_CH2 fails once TIM2_CH1 takes PA1, USART2_TX has pins to spare
+        match pins.assign_peripheral_pins(&["USART2_TX", "TIM2_CH1", "TIM2_CH2"]) {
+            Err(Error(ErrorKind::NoPinAvailable(signal), _)) => assert_eq!(signal, "TIM2_CH2"),
+            _ => panic!("TIM2_CH1 and TIM2_CH2 share the only pin"),
+        }
+    }
+
+    #[test]
+    fn assign_peripheral_pins_aliased() {
+        let mut pins = Pins {
+            pins: vec![
+                io(1, "PA9", &["TIM1_CH2"]),
+                io(2, "PA9 [PA11]", &["USART1_TX"]),
+                io(3, "PB6", &["USART1_TX"]),
+            ],
+        };
+        pins.pins[0].params_mut().unwrap().select_signal("TIM1_CH2");
+
+        // PA9 is taken by TIM1_CH2 configured before, its alias can't carry USART1_TX
+        let found = pins.assign_peripheral_pins(&["USART1_TX"]).unwrap();
+        assert_eq!(pin_names(&pins, &found), vec!["PB6"]);
+    }
 }
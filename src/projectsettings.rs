@@ -1,6 +1,12 @@
+use std::fs::{self, File};
+use std::io::Write;
 use std::path::{Path, PathBuf};
 
+use errors::*;
+use irqs::Interrupt;
+use linker::LinkerScript;
 use mcu::MCUConf;
+use vectors::VectorTable;
 
 use cargo::Cargo;
 
@@ -111,12 +117,41 @@ impl ProjectSettings {
     pub fn get_diff3_path(&self) -> &Path {
         &self.diff3_path.as_path()
     }
+
+    // Writes handlers of interrupts enabled on MCU into src/interrupts.rs of the project
+    pub fn generate_interrupts(&self, mcu_conf: &MCUConf, interrupts: &[Interrupt]) -> Result<()> {
+        let table = VectorTable::new(mcu_conf.get_name(), interrupts);
+        let src_path = self.project_path.join("src");
+
+        fs::create_dir_all(&src_path)?;
+
+        let mut file = File::create(src_path.join("interrupts.rs"))?;
+        let handlers = table.rust_handlers(mcu_conf.get_nvic(), self.cargo.get_pac());
+        file.write_all(handlers.as_bytes())?;
+
+        Ok(())
+    }
+
+    // Writes memory.x picked up by cortex-m-rt into root of the project
+    pub fn generate_memory_x(&self, mcu_conf: &MCUConf) -> Result<()> {
+        let script = LinkerScript::new(mcu_conf);
+
+        fs::create_dir_all(&self.project_path)?;
+
+        let mut file = File::create(self.project_path.join("memory.x"))?;
+        file.write_all(script.memory_x().as_bytes())?;
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use std::env;
+    use std::process;
+    use irqs::InterruptBuilder;
     use mcu::MCU;
 
     #[test]
@@ -132,4 +167,63 @@ mod tests {
         let project_settings =
             ProjectSettings::new(&mcu_conf, &project_path, &templates_path, &diff3_path);
     }
+
+    // Project directory of its own per test and process, stale one is removed first
+    fn project_dir(test: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("scrapyard-{}-{}", test, process::id()));
+
+        if path.exists() {
+            fs::remove_dir_all(&path).unwrap();
+        }
+
+        path
+    }
+
+    #[test]
+    fn generate_interrupts() {
+        let sample = Path::new("./samples/STM32F030C6Tx.json");
+        let mcu = MCU::new(sample).unwrap();
+        let sample = Path::new("./samples/NVIC-STM32F042_Modes.json");
+        let interrupts = InterruptBuilder::new(sample).unwrap().finish().unwrap();
+
+        let tim1 = interrupts
+            .iter()
+            .find(|i| i.irqn == "TIM1_BRK_UP_TRG_COM_IRQn")
+            .unwrap();
+
+        let mut mcu_conf = mcu.finish();
+        mcu_conf.get_nvic_mut().enable(tim1, 1, 0).unwrap();
+
+        let project_path = project_dir("generate-interrupts");
+        let path = Path::new("");
+
+        let project_settings = ProjectSettings::new(&mcu_conf, &project_path, &path, &path);
+        project_settings
+            .generate_interrupts(&mcu_conf, &interrupts)
+            .unwrap();
+
+        let generated = fs::read_to_string(project_path.join("src/interrupts.rs")).unwrap();
+        assert!(generated.contains("fn TIM1_BRK_UP_TRG_COM()"));
+        assert!(generated.contains("use stm32f0::interrupt;"));
+        assert!(!generated.contains("use cortex_m_rt::exception;"));
+
+        fs::remove_dir_all(&project_path).unwrap();
+    }
+
+    #[test]
+    fn generate_memory_x() {
+        let sample = Path::new("./samples/STM32F030C6Tx.json");
+        let mcu_conf = MCU::new(sample).unwrap().finish();
+
+        let project_path = project_dir("generate-memory-x");
+        let path = Path::new("");
+
+        let project_settings = ProjectSettings::new(&mcu_conf, &project_path, &path, &path);
+        project_settings.generate_memory_x(&mcu_conf).unwrap();
+
+        let generated = fs::read_to_string(project_path.join("memory.x")).unwrap();
+        assert!(generated.contains("RAM : ORIGIN = 0x20000000, LENGTH = 4K"));
+
+        fs::remove_dir_all(&project_path).unwrap();
+    }
 }
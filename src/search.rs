@@ -0,0 +1,248 @@
+use database::{IPInstances, McuDatabase, McuInfo};
+use mcu::{Core, Frequency};
+
+// McuQuery class
+//
+//    Parametric filter over the MCU catalogue. Every criterion is optional, unset
+//    criteria match any part. Results are ranked so that the smallest part satisfying
+//    the query comes first.
+//
+//    \sa McuDatabase, McuInfo
+#[derive(Debug)]
+pub struct McuQuery {
+    min_flash: u32,
+    min_ram: u32,
+    min_frequency: u16,
+    min_ios: u16,
+    core: Option<Core>,
+    package: Option<String>,
+    min_pins: Option<u16>,
+    max_pins: Option<u16>,
+    ips: Vec<IPInstances>,
+}
+
+impl Default for McuQuery {
+    fn default() -> McuQuery {
+        McuQuery::new()
+    }
+}
+
+impl McuQuery {
+    pub fn new() -> McuQuery {
+        McuQuery {
+            min_flash: 0,
+            min_ram: 0,
+            min_frequency: 0,
+            min_ios: 0,
+            core: None,
+            package: None,
+            min_pins: None,
+            max_pins: None,
+            ips: Vec::new(),
+        }
+    }
+
+    // Minimal Flash size in bytes
+    pub fn min_flash(mut self, size: u32) -> McuQuery {
+        self.min_flash = size;
+        self
+    }
+
+    // Minimal RAM size in bytes
+    pub fn min_ram(mut self, size: u32) -> McuQuery {
+        self.min_ram = size;
+        self
+    }
+
+    // Minimal core frequency in MHz
+    pub fn min_frequency(mut self, mhz: u16) -> McuQuery {
+        self.min_frequency = mhz;
+        self
+    }
+
+    pub fn min_ios(mut self, ios: u16) -> McuQuery {
+        self.min_ios = ios;
+        self
+    }
+
+    pub fn core(mut self, core: Core) -> McuQuery {
+        self.core = Some(core);
+        self
+    }
+
+    // Package family such as "LQFP" or "UFBGA"
+    pub fn package(mut self, family: &str) -> McuQuery {
+        self.package = Some(family.to_owned());
+        self
+    }
+
+    pub fn min_pins(mut self, pins: u16) -> McuQuery {
+        self.min_pins = Some(pins);
+        self
+    }
+
+    pub fn max_pins(mut self, pins: u16) -> McuQuery {
+        self.max_pins = Some(pins);
+        self
+    }
+
+    // Requires at least `count` instances of IP such as "USART" or "CAN"
+    pub fn ip(mut self, ip_type: &str, count: u16) -> McuQuery {
+        self.ips.push(IPInstances {
+            ip_type: ip_type.to_owned(),
+            count: count,
+        });
+        self
+    }
+
+    pub fn matches(&self, mcu: &McuInfo) -> bool {
+        if mcu.flash_size() < self.min_flash || mcu.ram_size() < self.min_ram {
+            return false;
+        }
+
+        if mcu.ios < self.min_ios {
+            return false;
+        }
+
+        // Minimum past u32::MAX Hz can't be met by any MCU
+        if self.min_frequency > 0 {
+            let min_frequency = Frequency::checked_from_mhz(u32::from(self.min_frequency));
+
+            match (mcu.frequency, min_frequency) {
+                (Some(frequency), Some(min)) if frequency >= min => (),
+                _ => return false,
+            }
+        }
+
+        if let Some(ref core) = self.core {
+            if mcu.core != *core {
+                return false;
+            }
+        }
+
+        if let Some(ref family) = self.package {
+            if mcu.package.family() != family {
+                return false;
+            }
+        }
+
+        let pins = mcu.package.pins();
+
+        if self.min_pins.map_or(false, |min| pins < min) {
+            return false;
+        }
+
+        if self.max_pins.map_or(false, |max| pins > max) {
+            return false;
+        }
+
+        self.ips
+            .iter()
+            .all(|ip| mcu.ip_count(&ip.ip_type) >= ip.count)
+    }
+
+    // Returns matching parts, smallest Flash, RAM and package first
+    pub fn search<'a>(&self, db: &'a McuDatabase) -> Vec<&'a McuInfo> {
+        let mut mcus: Vec<&McuInfo> = db.mcus()
+            .into_iter()
+            .filter(|mcu| self.matches(mcu))
+            .collect();
+
+        mcus.sort_by(|a, b| {
+            (a.flash_size(), a.ram_size(), a.package.pins(), &a.name).cmp(&(
+                b.flash_size(),
+                b.ram_size(),
+                b.package.pins(),
+                &b.name,
+            ))
+        });
+
+        mcus
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::path::Path;
+    use mcu::ARMCore;
+
+    #[test]
+    fn query_all() {
+        let sample = Path::new("./samples/mcus.json");
+        let db = McuDatabase::new(sample).unwrap();
+
+        let found = McuQuery::new().search(&db);
+
+        assert_eq!(964, found.len());
+    }
+
+    #[test]
+    fn query_ranked() {
+        let sample = Path::new("./samples/mcus.json");
+        let db = McuDatabase::new(sample).unwrap();
+
+        let found = McuQuery::new()
+            .core(Core::ARM(ARMCore::CortexM0))
+            .package("LQFP")
+            .max_pins(48)
+            .search(&db);
+
+        assert!(found.len() > 1);
+        assert_eq!(found[0].name, "STM32F031C(4-6)Tx");
+
+        for pair in found.windows(2) {
+            assert!(pair[0].flash_size() <= pair[1].flash_size());
+        }
+    }
+
+    #[test]
+    fn query_ips() {
+        let sample = Path::new("./samples/mcus.json");
+        let db = McuDatabase::new(sample).unwrap();
+
+        let found = McuQuery::new()
+            .min_flash(128 * 1024)
+            .min_ram(32 * 1024)
+            .ip("USART", 2)
+            .ip("CAN", 1)
+            .search(&db);
+
+        assert!(!found.is_empty());
+
+        for mcu in found {
+            assert!(mcu.flash_size() >= 128 * 1024);
+            assert!(mcu.ram_size() >= 32 * 1024);
+            assert!(mcu.ip_count("USART") >= 2);
+            assert!(mcu.ip_count("CAN") >= 1);
+        }
+    }
+
+    #[test]
+    fn query_none() {
+        let sample = Path::new("./samples/mcus.json");
+        let db = McuDatabase::new(sample).unwrap();
+
+        let found = McuQuery::new().ip("Ethernet", 1).max_pins(48).search(&db);
+
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn query_frequency() {
+        let sample = Path::new("./samples/mcus.json");
+        let db = McuDatabase::new(sample).unwrap();
+
+        let found = McuQuery::new().min_frequency(180).search(&db);
+        assert!(!found.is_empty());
+        assert!(found
+            .iter()
+            .all(|mcu| mcu.frequency >= Some(Frequency::from_mhz(180))));
+
+        assert!(McuQuery::new()
+            .min_frequency(u16::MAX)
+            .search(&db)
+            .is_empty());
+    }
+}
@@ -0,0 +1,196 @@
+use serde::{Deserialize, Deserializer};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum SignalKind {
+    Analog,
+    AlternateFunction,
+    Exti,
+    Gpio,
+}
+
+// Peripheral instances with underscores in their names, others end at the first one
+const INSTANCES: [&str; 3] = ["USB_OTG_FS", "USB_OTG_HS", "USB_DRD_FS"];
+
+// Signals are stored either as plain name or as full structure
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawSignal {
+    Name(String),
+    Full { name: String, af: Option<u8> },
+}
+
+// Signal class
+//
+//    Function which can be connected to a pin such as "USART1_TX". The name is split
+//    into peripheral instance ("USART1") and function ("TX"), instances listed in
+//    INSTANCES such as "USB_OTG_FS" are kept whole.
+//
+//    \sa IOPin
+#[derive(Serialize, Debug, PartialEq, Clone)]
+pub struct Signal {
+    name: String,
+    instance: String,
+    function: String,
+    af: Option<u8>,
+    kind: SignalKind,
+}
+
+impl Signal {
+    pub fn new(name: &str) -> Signal {
+        let known = INSTANCES.iter().find(|instance| {
+            name.starts_with(*instance)
+                && (name.len() == instance.len() || name[instance.len()..].starts_with('_'))
+        });
+
+        let split = match known {
+            Some(instance) if name.len() == instance.len() => None,
+            Some(instance) => Some(instance.len()),
+            None => name.find('_'),
+        };
+
+        let (instance, function) = match split {
+            Some(idx) => (&name[..idx], &name[idx + 1..]),
+            None => (name, ""),
+        };
+
+        Signal {
+            name: name.to_owned(),
+            instance: instance.to_owned(),
+            function: function.to_owned(),
+            af: None,
+            kind: Signal::classify(instance, function),
+        }
+    }
+
+    fn classify(instance: &str, function: &str) -> SignalKind {
+        if instance == "GPIO" && function.is_empty() {
+            return SignalKind::Gpio;
+        }
+
+        if function.starts_with("EXTI") {
+            return SignalKind::Exti;
+        }
+
+        let analog = ["ADC", "DAC", "COMP", "OPAMP", "SDADC"]
+            .iter()
+            .any(|prefix| instance.starts_with(prefix));
+
+        let oscillator = instance == "RCC" && function.starts_with("OSC");
+
+        if analog || oscillator {
+            SignalKind::Analog
+        } else {
+            SignalKind::AlternateFunction
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn instance(&self) -> &str {
+        &self.instance
+    }
+
+    pub fn function(&self) -> &str {
+        &self.function
+    }
+
+    pub fn kind(&self) -> SignalKind {
+        self.kind
+    }
+
+    pub fn af(&self) -> Option<u8> {
+        self.af
+    }
+
+    pub fn set_af(&mut self, af: u8) {
+        self.af = Some(af);
+    }
+}
+
+impl PartialEq<String> for Signal {
+    fn eq(&self, other: &String) -> bool {
+        self.name == *other
+    }
+}
+
+impl<'de> Deserialize<'de> for Signal {
+    fn deserialize<D>(deserializer: D) -> Result<Signal, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match RawSignal::deserialize(deserializer)? {
+            RawSignal::Name(name) => Ok(Signal::new(&name)),
+            RawSignal::Full { name, af } => {
+                let mut signal = Signal::new(&name);
+                signal.af = af;
+                Ok(signal)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn signal_split() {
+        let signal = Signal::new("USART1_TX");
+
+        assert_eq!(signal.instance(), "USART1");
+        assert_eq!(signal.function(), "TX");
+        assert_eq!(signal.kind(), SignalKind::AlternateFunction);
+        assert_eq!(signal.af(), None);
+    }
+
+    #[test]
+    fn signal_split_known_instance() {
+        let signal = Signal::new("USB_OTG_FS_DM");
+        assert_eq!(signal.instance(), "USB_OTG_FS");
+        assert_eq!(signal.function(), "DM");
+
+        let signal = Signal::new("USB_OTG_HS_ULPI_D0");
+        assert_eq!(signal.instance(), "USB_OTG_HS");
+        assert_eq!(signal.function(), "ULPI_D0");
+
+        let signal = Signal::new("SYS_JTCK-SWCLK");
+        assert_eq!(signal.instance(), "SYS");
+        assert_eq!(signal.function(), "JTCK-SWCLK");
+
+        assert_eq!(Signal::new("USB_OTG_FS").function(), "");
+        assert_eq!(Signal::new("USB_DM").instance(), "USB");
+    }
+
+    #[test]
+    fn signal_kind() {
+        assert_eq!(Signal::new("GPIO").kind(), SignalKind::Gpio);
+        assert_eq!(Signal::new("ADC_IN0").kind(), SignalKind::Analog);
+        assert_eq!(Signal::new("RCC_OSC32_IN").kind(), SignalKind::Analog);
+        assert_eq!(Signal::new("GPIO_EXTI13").kind(), SignalKind::Exti);
+        assert_eq!(Signal::new("TIM1_CH1N").kind(), SignalKind::AlternateFunction);
+        assert_eq!(Signal::new("CEC").function(), "");
+    }
+
+    #[test]
+    fn signal_from_name() {
+        let signal: Signal = serde_json::from_str(r#""TIM1_CH1N""#).unwrap();
+
+        assert_eq!(signal.instance(), "TIM1");
+        assert_eq!(signal.function(), "CH1N");
+    }
+
+    #[test]
+    fn signal_roundtrip() {
+        let mut signal = Signal::new("USART2_RX");
+        signal.set_af(7);
+
+        let json = serde_json::to_string(&signal).unwrap();
+        let parsed: Signal = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(signal, parsed);
+    }
+}
@@ -0,0 +1,302 @@
+use std::fmt::Write;
+
+use regex::Regex;
+
+use irqs::Interrupt;
+use nvic::NvicConfig;
+
+// Core exceptions with their CMSIS handler and cortex-m-rt exception names and IRQ
+// numbers
+const EXCEPTIONS: [(&str, &str, &str, i16); 10] = [
+    ("NonMaskableInt_IRQn", "NMI_Handler", "NonMaskableInt", -14),
+    ("HardFault_IRQn", "HardFault_Handler", "HardFault", -13),
+    (
+        "MemoryManagement_IRQn",
+        "MemManage_Handler",
+        "MemoryManagement",
+        -12,
+    ),
+    ("BusFault_IRQn", "BusFault_Handler", "BusFault", -11),
+    ("UsageFault_IRQn", "UsageFault_Handler", "UsageFault", -10),
+    ("SVC_IRQn", "SVC_Handler", "SVCall", -5),
+    ("SVCall_IRQn", "SVC_Handler", "SVCall", -5),
+    ("DebugMonitor_IRQn", "DebugMon_Handler", "DebugMonitor", -4),
+    ("PendSV_IRQn", "PendSV_Handler", "PendSV", -2),
+    ("SysTick_IRQn", "SysTick_Handler", "SysTick", -1),
+];
+
+pub fn is_exception(interrupt: &Interrupt) -> bool {
+    EXCEPTIONS.iter().any(|e| e.0 == interrupt.irqn)
+}
+
+// CMSIS handler name such as "USART1_IRQHandler" or "SysTick_Handler"
+pub fn c_handler_name(interrupt: &Interrupt) -> String {
+    match EXCEPTIONS.iter().find(|e| e.0 == interrupt.irqn) {
+        Some(exception) => exception.1.to_string(),
+        None => format!("{}_IRQHandler", interrupt.name()),
+    }
+}
+
+// Handler name as used by cortex-m-rt #[exception] and #[interrupt] attributes
+pub fn rust_handler_name(interrupt: &Interrupt) -> String {
+    match EXCEPTIONS.iter().find(|e| e.0 == interrupt.irqn) {
+        Some(exception) => exception.2.to_string(),
+        None => interrupt.name().to_string(),
+    }
+}
+
+// IRQ numbers of IRQn_Type enumeration of CMSIS device header such as stm32f042x6.h
+pub fn irq_numbers(header: &str) -> Vec<(String, i16)> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"(?m)^\s*(\w+_IRQn)\s*=\s*(-?\d+)").unwrap();
+    }
+
+    RE.captures_iter(header)
+        .filter_map(|caps| caps[2].parse().ok().map(|n| (caps[1].to_string(), n)))
+        .collect()
+}
+
+// VectorTable class
+//
+//    Interrupts of the device in the order of the NVIC mode file, entries describing the
+//    same IRQn are merged. The mode file carries no IRQ numbers, device interrupts are
+//    numbered from the CMSIS device header.
+//
+//    \sa Interrupt, NvicConfig
+#[derive(Debug)]
+pub struct VectorTable<'a> {
+    name: String,
+    entries: Vec<&'a Interrupt>,
+    numbers: Vec<(String, i16)>,
+}
+
+impl<'a> VectorTable<'a> {
+    pub fn new(name: &str, interrupts: &'a [Interrupt]) -> VectorTable<'a> {
+        let mut entries: Vec<&Interrupt> = vec![];
+
+        for interrupt in interrupts {
+            if !entries.iter().any(|e| e.irqn == interrupt.irqn) {
+                entries.push(interrupt);
+            }
+        }
+
+        VectorTable {
+            name: name.to_owned(),
+            entries: entries,
+            numbers: EXCEPTIONS.iter().map(|e| (e.0.to_string(), e.3)).collect(),
+        }
+    }
+
+    // Takes device IRQ numbers from the CMSIS device header
+    pub fn set_irq_numbers(&mut self, header: &str) {
+        for (irqn, number) in irq_numbers(header) {
+            match self.numbers.iter_mut().find(|n| n.0 == irqn) {
+                Some(known) => known.1 = number,
+                None => self.numbers.push((irqn, number)),
+            }
+        }
+    }
+
+    pub fn number(&self, irqn: &str) -> Option<i16> {
+        self.numbers.iter().find(|n| n.0 == irqn).map(|n| n.1)
+    }
+
+    pub fn entries(&self) -> &Vec<&'a Interrupt> {
+        &self.entries
+    }
+
+    fn enabled(&self, nvic: &NvicConfig) -> Vec<&'a Interrupt> {
+        self.entries
+            .iter()
+            .filter(|e| nvic.is_enabled(&e.irqn))
+            .cloned()
+            .collect()
+    }
+
+    // Listing of handler names in device order, core exceptions first
+    pub fn listing(&self) -> String {
+        let mut out = String::new();
+
+        writeln!(out, "Vector table of {}", self.name).unwrap();
+        writeln!(out).unwrap();
+
+        let exceptions = self.entries.iter().filter(|e| is_exception(e));
+        let interrupts = self.entries.iter().filter(|e| !is_exception(e));
+
+        for entry in exceptions.chain(interrupts) {
+            let number = match self.number(&entry.irqn) {
+                Some(number) => number.to_string(),
+                None => String::from("-"),
+            };
+
+            writeln!(
+                out,
+                "{:>4} {:<32} {}",
+                number,
+                c_handler_name(entry),
+                entry.desc
+            )
+            .unwrap();
+        }
+
+        out
+    }
+
+    // Skeleton of cortex-m-rt handlers for enabled interrupts, the interrupt attribute
+    // comes from the peripheral access crate
+    pub fn rust_handlers(&self, nvic: &NvicConfig, pac: &str) -> String {
+        let mut out = String::new();
+        let enabled = self.enabled(nvic);
+
+        writeln!(out, "// Interrupt handlers of {}", self.name).unwrap();
+        writeln!(out).unwrap();
+
+        if enabled.iter().any(|e| is_exception(e)) {
+            writeln!(out, "use cortex_m_rt::exception;").unwrap();
+        }
+        if enabled.iter().any(|e| !is_exception(e)) {
+            writeln!(out, "use {}::interrupt;", pac).unwrap();
+        }
+
+        for entry in enabled {
+            writeln!(out).unwrap();
+            writeln!(out, "// {}", entry.desc).unwrap();
+
+            if is_exception(entry) {
+                writeln!(out, "#[exception]").unwrap();
+            } else {
+                writeln!(out, "#[interrupt]").unwrap();
+            }
+
+            writeln!(out, "fn {}() {{", rust_handler_name(entry)).unwrap();
+
+            for handler in &entry.handlers {
+                writeln!(out, "    // TODO: {}", handler).unwrap();
+            }
+
+            writeln!(out, "}}").unwrap();
+        }
+
+        out
+    }
+
+    // C handlers for enabled interrupts with HAL handler calls left to the user
+    pub fn c_handlers(&self, nvic: &NvicConfig) -> String {
+        let mut out = String::new();
+
+        writeln!(out, "/* Interrupt handlers of {} */", self.name).unwrap();
+
+        for entry in self.enabled(nvic) {
+            writeln!(out).unwrap();
+            writeln!(out, "/* {} */", entry.desc).unwrap();
+            writeln!(out, "void {}(void)", c_handler_name(entry)).unwrap();
+            writeln!(out, "{{").unwrap();
+
+            for handler in &entry.handlers {
+                writeln!(out, "    /* TODO: {} */", handler).unwrap();
+            }
+
+            writeln!(out, "}}").unwrap();
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use irqs::InterruptBuilder;
+    use mcu::{ARMCore, Core};
+    use std::path::Path;
+
+    fn interrupts() -> Vec<Interrupt> {
+        let sample = Path::new("./samples/NVIC-STM32F042_Modes.json");
+        InterruptBuilder::new(sample).unwrap().finish().unwrap()
+    }
+
+    fn find<'a>(interrupts: &'a [Interrupt], irqn: &str) -> &'a Interrupt {
+        interrupts.iter().find(|i| i.irqn == irqn).unwrap()
+    }
+
+    #[test]
+    fn handler_names() {
+        let interrupts = interrupts();
+
+        let nmi = find(&interrupts, "NonMaskableInt_IRQn");
+        assert_eq!(c_handler_name(nmi), "NMI_Handler");
+        assert_eq!(rust_handler_name(nmi), "NonMaskableInt");
+
+        let svc = find(&interrupts, "SVC_IRQn");
+        assert_eq!(c_handler_name(svc), "SVC_Handler");
+        assert_eq!(rust_handler_name(svc), "SVCall");
+
+        let wwdg = find(&interrupts, "WWDG_IRQn");
+        assert_eq!(c_handler_name(wwdg), "WWDG_IRQHandler");
+        assert_eq!(rust_handler_name(wwdg), "WWDG");
+    }
+
+    #[test]
+    fn irq_numbers_from_header() {
+        let interrupts = interrupts();
+        let mut table = VectorTable::new("STM32F042", &interrupts);
+
+        assert_eq!(table.number("SysTick_IRQn"), Some(-1));
+        assert_eq!(table.number("USART1_IRQn"), None);
+
+        table.set_irq_numbers(
+            "typedef enum\n{\n  NonMaskableInt_IRQn = -14,\n  SysTick_IRQn = -1,\n\
+             WWDG_IRQn = 0,\n  USART1_IRQn = 27,  /*!< USART1 global Interrupt */\n} IRQn_Type;",
+        );
+
+        assert_eq!(table.number("WWDG_IRQn"), Some(0));
+        assert_eq!(table.number("USART1_IRQn"), Some(27));
+
+        let listing = table.listing();
+        assert!(listing.contains(" -14 NMI_Handler"));
+        assert!(listing.contains("  27 USART1_IRQHandler"));
+        assert!(listing.contains("   - USB_IRQHandler"));
+    }
+
+    #[test]
+    fn vector_table_dedup() {
+        let interrupts = interrupts();
+        let table = VectorTable::new("STM32F042", &interrupts);
+
+        assert_eq!(table.entries().len(), 34);
+
+        let listing = table.listing();
+        assert!(listing.starts_with("Vector table of STM32F042"));
+        assert_eq!(listing.matches("USART1_IRQHandler").count(), 1);
+        assert!(
+            listing.find("SysTick_Handler").unwrap() < listing.find("WWDG_IRQHandler").unwrap()
+        );
+    }
+
+    #[test]
+    fn generate_handlers() {
+        let interrupts = interrupts();
+        let table = VectorTable::new("STM32F042", &interrupts);
+        let mut nvic = NvicConfig::new(&Core::ARM(ARMCore::CortexM0));
+
+        nvic.enable(find(&interrupts, "TIM2_IRQn"), 1, 0).unwrap();
+
+        let rust = table.rust_handlers(&nvic, "stm32f0");
+        assert!(rust.contains("use stm32f0::interrupt;"));
+        assert!(!rust.contains("use cortex_m_rt::exception;"));
+
+        nvic.enable(find(&interrupts, "SysTick_IRQn"), 0, 0)
+            .unwrap();
+
+        let rust = table.rust_handlers(&nvic, "stm32f0");
+        assert!(rust.contains("use cortex_m_rt::exception;"));
+        assert!(rust.contains("#[exception]\nfn SysTick() {\n}"));
+        assert!(rust.contains("#[interrupt]\nfn TIM2() {\n    // TODO: HAL_TIM_IRQHandler\n}"));
+        assert!(!rust.contains("fn TIM3()"));
+
+        let c = table.c_handlers(&nvic);
+        assert!(c.contains("void SysTick_Handler(void)"));
+        assert!(c.contains("void TIM2_IRQHandler(void)\n{\n    /* TODO: HAL_TIM_IRQHandler */\n}"));
+    }
+}